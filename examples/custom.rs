@@ -1,7 +1,7 @@
 extern crate term_painter;
 
 use term_painter::ToStyle;
-use term_painter::Color::Custom;
+use term_painter::Color::{Custom, Rgb};
 use term_painter::Attr::Plain;
 
 fn main() {
@@ -12,13 +12,19 @@ fn main() {
         for c in (0..16).map(|i| 16*line + i) {
             print!("{: <2x} ", Custom(c).paint(c));
         }
-        println!("");
+        println!();
 
         // background
         print!("BG:  ");
         for c in (0..16).map(|i| 16*line + i) {
             print!("{: <2x} ", Plain.bg(Custom(c)).paint(c));
         }
-        println!("");
+        println!();
     }
+
+    // a truecolor gradient
+    for i in 0..64 {
+        print!("{}", Plain.bg(Rgb(i * 4, 0, 255 - i * 4)).paint(" "));
+    }
+    println!();
 }
@@ -124,4 +124,22 @@ fn all_styles(colors: &[Color]) {
     for c in colors { print!("{:?} ", Secure.bg(*c).paint(c)); }
     println!("    (secure bg)");
 
+    // Italic text
+    for c in colors { print!("{:?} ", c.italic().paint(c)); }
+    println!("    (italic fg)");
+    for c in colors { print!("{:?} ", Italic.bg(*c).paint(c)); }
+    println!("    (italic bg)");
+
+    // Strikethrough text
+    for c in colors { print!("{:?} ", c.strikethrough().paint(c)); }
+    println!("    (strikethrough fg)");
+    for c in colors { print!("{:?} ", Strikethrough.bg(*c).paint(c)); }
+    println!("    (strikethrough bg)");
+
+    // Overlined text
+    for c in colors { print!("{:?} ", c.overline().paint(c)); }
+    println!("    (overline fg)");
+    for c in colors { print!("{:?} ", Overline.bg(*c).paint(c)); }
+    println!("    (overline bg)");
+
 }
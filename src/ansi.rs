@@ -0,0 +1,380 @@
+//! Rendering of styles as ANSI escape sequences. These are the SGR ("Select
+//! Graphic Rendition") sequences of ECMA-48, which are understood by nearly
+//! all terminals (and terminal emulators) out there.
+
+use std::fmt;
+use std::io;
+
+use {Attr, Color, Style, UnderlineStyle};
+
+/// Resets all attributes and colors.
+pub const RESET: &str = "\x1b[0m";
+
+/// Ends a hyperlink started by `write_link_start`.
+pub const LINK_END: &str = "\x1b]8;;\x1b\\";
+
+/// Starts a hyperlink to `url` (OSC 8). Control characters are removed from
+/// `url` as they could terminate the sequence early.
+pub fn write_link_start<W: fmt::Write + ?Sized>(w: &mut W, url: &str) -> fmt::Result {
+    w.write_str("\x1b]8;;")?;
+    for c in url.chars().filter(|c| !c.is_control()) {
+        w.write_char(c)?;
+    }
+    w.write_str("\x1b\\")
+}
+
+/// Helper to write a list of SGR parameters separated by `;`. The leading
+/// `ESC [` is only written when the first parameter is pushed, so nothing is
+/// written at all if no parameter is pushed.
+struct Sgr<'a, W: fmt::Write + ?Sized + 'a> {
+    w: &'a mut W,
+    empty: bool,
+}
+
+impl<'a, W: fmt::Write + ?Sized> Sgr<'a, W> {
+    fn new(w: &'a mut W) -> Self {
+        Sgr { w, empty: true }
+    }
+
+    fn push(&mut self, args: fmt::Arguments) -> fmt::Result {
+        self.w.write_str(if self.empty { "\x1b[" } else { ";" })?;
+        self.empty = false;
+        self.w.write_fmt(args)
+    }
+
+    fn color(&mut self, c: Color, base: u8) -> fmt::Result {
+        // `base` is 30 for the foreground and 40 for the background color.
+        // The bright colors use `base + 60` and the extended palette is
+        // addressed via `base + 8`.
+        match c {
+            Color::NotSet => Ok(()),
+            Color::Black => self.push(format_args!("{}", base)),
+            Color::Red => self.push(format_args!("{}", base + 1)),
+            Color::Green => self.push(format_args!("{}", base + 2)),
+            Color::Yellow => self.push(format_args!("{}", base + 3)),
+            Color::Blue => self.push(format_args!("{}", base + 4)),
+            Color::Magenta => self.push(format_args!("{}", base + 5)),
+            Color::Cyan => self.push(format_args!("{}", base + 6)),
+            Color::White => self.push(format_args!("{}", base + 7)),
+            Color::BrightBlack => self.push(format_args!("{}", base + 60)),
+            Color::BrightRed => self.push(format_args!("{}", base + 61)),
+            Color::BrightGreen => self.push(format_args!("{}", base + 62)),
+            Color::BrightYellow => self.push(format_args!("{}", base + 63)),
+            Color::BrightBlue => self.push(format_args!("{}", base + 64)),
+            Color::BrightMagenta => self.push(format_args!("{}", base + 65)),
+            Color::BrightCyan => self.push(format_args!("{}", base + 66)),
+            Color::BrightWhite => self.push(format_args!("{}", base + 67)),
+            // Palette indices beyond 255 can't be expressed with SGR.
+            Color::Custom(c) if c < 256 => self.push(format_args!("{};5;{}", base + 8, c)),
+            Color::Custom(_) => Ok(()),
+            Color::Rgb(r, g, b) => self.push(format_args!("{};2;{};{};{}", base + 8, r, g, b)),
+        }
+    }
+
+    fn underline_color(&mut self, c: Color) -> fmt::Result {
+        // There are no short codes for the underline color, so the named
+        // colors are addressed via their palette index.
+        match (c, c.term_constant()) {
+            (Color::Rgb(r, g, b), _) => self.push(format_args!("58;2;{};{};{}", r, g, b)),
+            (_, Some(idx)) if idx < 256 => self.push(format_args!("58;5;{}", idx)),
+            _ => Ok(()),
+        }
+    }
+
+    fn finish(self) -> fmt::Result {
+        if self.empty {
+            Ok(())
+        } else {
+            self.w.write_str("m")
+        }
+    }
+}
+
+/// Writes the escape sequence that applies all properties set in `style`.
+/// Like `Style::apply`, this doesn't reset anything, so properties that are
+/// not set in `style` are left untouched. Nothing is written if `style`
+/// doesn't set anything.
+pub fn write_style<W: fmt::Write + ?Sized>(w: &mut W, style: &Style) -> fmt::Result {
+    let mut sgr = Sgr::new(w);
+    push_props(&mut sgr, style)?;
+    sgr.finish()
+}
+
+fn push_props<W: fmt::Write + ?Sized>(sgr: &mut Sgr<W>, style: &Style) -> fmt::Result {
+    sgr.color(style.fg, 30)?;
+    sgr.color(style.bg, 40)?;
+
+    if let Some(true) = style.get_bold() {
+        sgr.push(format_args!("1"))?;
+    }
+    if let Some(true) = style.get_dim() {
+        sgr.push(format_args!("2"))?;
+    }
+    match (style.get_underline(), style.get_underline_style()) {
+        (Some(true), None) => sgr.push(format_args!("4"))?,
+        (Some(true), Some(u)) => {
+            let n = match u {
+                UnderlineStyle::Single => 1,
+                UnderlineStyle::Double => 2,
+                UnderlineStyle::Curly => 3,
+                UnderlineStyle::Dotted => 4,
+                UnderlineStyle::Dashed => 5,
+            };
+            sgr.push(format_args!("4:{}", n))?;
+        }
+        (Some(false), _) => sgr.push(format_args!("24"))?,
+        (None, _) => {}
+    }
+    sgr.underline_color(style.underline_color)?;
+    if let Some(true) = style.get_blink() {
+        sgr.push(format_args!("5"))?;
+    }
+    if let Some(true) = style.get_reverse() {
+        sgr.push(format_args!("7"))?;
+    }
+    if let Some(true) = style.get_secure() {
+        sgr.push(format_args!("8"))?;
+    }
+    if let Some(true) = style.get_italic() {
+        sgr.push(format_args!("3"))?;
+    }
+    if let Some(true) = style.get_strikethrough() {
+        sgr.push(format_args!("9"))?;
+    }
+    if let Some(true) = style.get_overline() {
+        sgr.push(format_args!("53"))?;
+    }
+
+    Ok(())
+}
+
+/// Pushes the codes that turn off everything `from` sets and `to` doesn't.
+/// Returns the properties that have to be applied afterwards to get `to`.
+/// Unset attributes and attributes set to `false` are treated the same, as
+/// both mean "off" on the terminal.
+fn push_off<W: fmt::Write + ?Sized>(
+    sgr: &mut Sgr<W>,
+    from: &Style,
+    to: &Style,
+) -> Result<Style, fmt::Error> {
+    let mut on = Style::default();
+
+    // Colors are set to the terminal's default color if unset in `to`
+    if from.fg != to.fg {
+        match to.fg {
+            Color::NotSet => sgr.push(format_args!("39"))?,
+            c => on.fg = c,
+        }
+    }
+    if from.bg != to.bg {
+        match to.bg {
+            Color::NotSet => sgr.push(format_args!("49"))?,
+            c => on.bg = c,
+        }
+    }
+    if from.underline_color != to.underline_color {
+        match to.underline_color {
+            Color::NotSet => sgr.push(format_args!("59"))?,
+            c => on.underline_color = c,
+        }
+    }
+
+    let is_on = |s: &Style, attr| s.get_attr(attr) == Some(true);
+
+    // Bold and dim are both turned off by 22, so one of them might have to
+    // be turned on again.
+    let bold_dim_off = [Attr::Bold, Attr::Dim].iter()
+        .any(|&a| is_on(from, a) && !is_on(to, a));
+    if bold_dim_off {
+        sgr.push(format_args!("22"))?;
+    }
+    for &attr in &[Attr::Bold, Attr::Dim] {
+        if is_on(to, attr) && (bold_dim_off || !is_on(from, attr)) {
+            on.set_attr(attr, Some(true));
+        }
+    }
+
+    for &(attr, off) in &[
+        (Attr::Italic, 23),
+        (Attr::Underline, 24),
+        (Attr::Blink, 25),
+        (Attr::Reverse, 27),
+        (Attr::Secure, 28),
+        (Attr::Strikethrough, 29),
+        (Attr::Overline, 55),
+    ] {
+        match (is_on(from, attr), is_on(to, attr)) {
+            (true, false) => sgr.push(format_args!("{}", off))?,
+            (false, true) => on.set_attr(attr, Some(true)),
+            _ => {}
+        }
+    }
+
+    // The underline style only matters while the text is underlined
+    let underline_style = |s: &Style| s.get_underline_style().unwrap_or(UnderlineStyle::Single);
+    if is_on(to, Attr::Underline)
+        && (!is_on(from, Attr::Underline) || underline_style(from) != underline_style(to))
+    {
+        on.set_underline(Some(true));
+        on.set_underline_style(to.get_underline_style());
+    }
+
+    Ok(on)
+}
+
+/// Writes the codes that turn off everything `from` sets and `to` doesn't
+/// (see `push_off`) and returns the properties that have to be applied
+/// afterwards.
+pub fn write_off<W: fmt::Write + ?Sized>(
+    w: &mut W,
+    from: &Style,
+    to: &Style,
+) -> Result<Style, fmt::Error> {
+    let mut sgr = Sgr::new(w);
+    let on = push_off(&mut sgr, from, to)?;
+    sgr.finish()?;
+    Ok(on)
+}
+
+/// Like `write_style`, but writes to an `io::Write` sink.
+pub fn write_io<W: io::Write + ?Sized>(w: &mut W, style: &Style) -> io::Result<()> {
+    let mut s = String::new();
+    write_style(&mut s, style).expect("writing into a `String` can't fail");
+    w.write_all(s.as_bytes())
+}
+
+/// Resets everything and applies `style` afterwards.
+fn write_revert_to<W: fmt::Write + ?Sized>(w: &mut W, style: &Style) -> fmt::Result {
+    w.write_str(RESET)?;
+    write_style(w, style)
+}
+
+/// Writes only the changes from `from` to `to` in a single sequence.
+fn write_diff<W: fmt::Write + ?Sized>(w: &mut W, from: &Style, to: &Style) -> fmt::Result {
+    let mut sgr = Sgr::new(w);
+    let on = push_off(&mut sgr, from, to)?;
+    push_props(&mut sgr, &on)?;
+    sgr.finish()
+}
+
+/// `fmt::Write` sink that only counts the bytes written to it.
+struct Count(usize);
+
+impl fmt::Write for Count {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+/// The shortest escape sequence that changes the style `from` into `to`.
+/// Created by `Style::transition`; use its `Display` implementation to
+/// write it:
+///
+/// ```
+/// # use term_painter::{Color, Style, ToStyle};
+/// let from = Color::Red.bold();
+/// assert_eq!(Style::transition(from, Color::Red.to_style()).to_string(), "\x1b[22m");
+/// assert_eq!(Style::transition(from, Color::Blue.bold()).to_string(), "\x1b[34m");
+/// assert_eq!(Style::transition(from, from).to_string(), "");
+/// ```
+///
+/// Only the properties that differ are written: turning an attribute off
+/// uses its specific code (like `22` for bold) instead of resetting
+/// everything. If resetting everything and applying `to` is shorter, that is
+/// written instead.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Transition {
+    from: Style,
+    to: Style,
+}
+
+impl Transition {
+    pub(crate) fn new(from: Style, to: Style) -> Self {
+        Transition { from, to }
+    }
+
+    /// Returns whether resetting everything and applying `to` is shorter
+    /// than changing only what differs.
+    pub(crate) fn resets(&self) -> bool {
+        let mut reset = Count(0);
+        let mut diff = Count(0);
+        write_revert_to(&mut reset, &self.to).expect("writing into a `Count` can't fail");
+        write_diff(&mut diff, &self.from, &self.to).expect("writing into a `Count` can't fail");
+
+        reset.0 < diff.0
+    }
+}
+
+impl fmt::Display for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.resets() {
+            write_revert_to(f, &self.to)
+        } else {
+            write_diff(f, &self.from, &self.to)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use Color::*;
+    use Attr::*;
+    use {ToStyle, Style, UnderlineStyle};
+    use super::{write_style, write_link_start};
+
+    fn render(s: Style) -> String {
+        let mut out = String::new();
+        write_style(&mut out, &s).unwrap();
+        out
+    }
+
+    #[test]
+    fn sgr_sequences() {
+        assert_eq!(render(Plain.to_style()), "");
+        assert_eq!(render(Red.to_style()), "\x1b[31m");
+        assert_eq!(render(Plain.bg(BrightBlue)), "\x1b[104m");
+        assert_eq!(render(Custom(208).bg(Custom(17))), "\x1b[38;5;208;48;5;17m");
+        assert_eq!(render(Rgb(255, 0, 10).bg(Rgb(0, 0, 0))), "\x1b[38;2;255;0;10;48;2;0;0;0m");
+        assert_eq!(render(Green.bold().underline()), "\x1b[32;1;4m");
+        assert_eq!(render(Plain.not_underline()), "\x1b[24m");
+        assert_eq!(render(Italic.strikethrough().overline()), "\x1b[3;9;53m");
+        assert_eq!(
+            render(Plain.underline_style(UnderlineStyle::Curly).underline_color(Red)),
+            "\x1b[4:3;58;5;1m",
+        );
+        assert_eq!(render(Plain.underline_color(Rgb(1, 2, 3))), "\x1b[58;2;1;2;3m");
+    }
+
+    #[test]
+    fn transitions() {
+        let t = |from: Style, to: Style| Style::transition(from, to).to_string();
+
+        assert_eq!(t(Red.bold(), Red.bold()), "");
+        assert_eq!(t(Red.bold(), Blue.bold().underline()), "\x1b[34;4m");
+        assert_eq!(t(Red.bold(), Red.bold().not_underline()), "");
+        assert_eq!(t(Red.bold(), Red.to_style()), "\x1b[22m");
+        assert_eq!(t(Red.bold().dim(), Red.dim()), "\x1b[22;2m");
+        assert_eq!(t(Red.bold().bg(Blue), Bold.bg(Blue)), "\x1b[39m");
+        assert_eq!(t(Italic.strikethrough().overline(), Italic.to_style()), "\x1b[29;55m");
+        assert_eq!(t(Plain.underline_color(Red), Plain.to_style()), "\x1b[0m");
+        assert_eq!(
+            t(Underline.to_style(), Plain.underline_style(UnderlineStyle::Dashed)),
+            "\x1b[4:5m",
+        );
+        assert_eq!(t(Plain.underline_style(UnderlineStyle::Single), Underline.to_style()), "");
+        assert_eq!(t(Bold.to_style(), Bold.underline_style(UnderlineStyle::Curly)), "\x1b[4:3m");
+
+        // Resetting is shorter here
+        assert_eq!(t(Red.bold().underline(), Plain.to_style()), "\x1b[0m");
+        assert_eq!(t(Red.bold().italic().blink(), Blue.to_style()), "\x1b[0m\x1b[34m");
+    }
+
+    #[test]
+    fn hyperlinks() {
+        let mut out = String::new();
+        write_link_start(&mut out, "https://example.com/\x1b\x07x").unwrap();
+        assert_eq!(out, "\x1b]8;;https://example.com/x\x1b\\");
+    }
+}
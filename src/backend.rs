@@ -0,0 +1,135 @@
+//! Choosing how styles are sent to the terminal.
+
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use term;
+
+use ansi;
+use output::Terminal;
+use {Color, ToStyle};
+
+/// How styles are sent to the terminal. Set it with `set_backend`.
+///
+/// ```
+/// # use term_painter::{set_backend, Backend};
+/// // The output ends up in ANSI-aware logs, even without terminfo
+/// set_backend(Backend::Ansi);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Backend {
+    /// Use `term` (terminfo or the Windows console) and fall back to `Ansi`
+    /// if `term` can't find a terminal, e.g. because `TERM` is not set or
+    /// there is no terminfo database (like in many containers).
+    Auto,
+    /// Only use `term`. Nothing is styled if `term` can't find a terminal.
+    Terminfo,
+    /// Write ANSI (ECMA-48) escape sequences directly, without consulting
+    /// terminfo. Nearly all terminals and many log viewers understand them.
+    Ansi,
+}
+
+impl Backend {
+    fn from_usize(v: usize) -> Self {
+        match v {
+            1 => Backend::Terminfo,
+            2 => Backend::Ansi,
+            _ => Backend::Auto,
+        }
+    }
+}
+
+static BACKEND: AtomicUsize = AtomicUsize::new(Backend::Auto as usize);
+
+/// Returns the backend that is currently used.
+pub fn backend() -> Backend {
+    Backend::from_usize(BACKEND.load(Ordering::Relaxed))
+}
+
+/// Sets the process-wide backend. The terminal handles of each thread are
+/// reopened the next time a style is applied.
+pub fn set_backend(backend: Backend) {
+    BACKEND.store(backend as usize, Ordering::Relaxed);
+}
+
+/// Terminal that writes ANSI escape sequences to `W`. It assumes that
+/// everything is supported.
+pub struct AnsiTerminal<W>(pub W);
+
+impl<W: Write> AnsiTerminal<W> {
+    fn sgr(&mut self, code: u8) -> term::Result<()> {
+        write!(self.0, "\x1b[{}m", code).map_err(term::Error::Io)
+    }
+}
+
+impl<W: Write> Write for AnsiTerminal<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl<W: Write> Terminal for AnsiTerminal<W> {
+    fn fg(&mut self, color: term::color::Color) -> term::Result<()> {
+        ansi::write_io(&mut self.0, &Color::from_index(color).to_style()).map_err(term::Error::Io)
+    }
+
+    fn bg(&mut self, color: term::color::Color) -> term::Result<()> {
+        let style = Color::NotSet.bg(Color::from_index(color));
+        ansi::write_io(&mut self.0, &style).map_err(term::Error::Io)
+    }
+
+    fn attr(&mut self, attr: term::Attr) -> term::Result<()> {
+        match attr {
+            term::Attr::Bold => self.sgr(1),
+            term::Attr::Dim => self.sgr(2),
+            term::Attr::Italic(true) => self.sgr(3),
+            term::Attr::Italic(false) => self.sgr(23),
+            term::Attr::Underline(true) => self.sgr(4),
+            term::Attr::Underline(false) => self.sgr(24),
+            term::Attr::Blink => self.sgr(5),
+            term::Attr::Standout(true) | term::Attr::Reverse => self.sgr(7),
+            term::Attr::Standout(false) => self.sgr(27),
+            term::Attr::Secure => self.sgr(8),
+            term::Attr::ForegroundColor(c) => self.fg(c),
+            term::Attr::BackgroundColor(c) => self.bg(c),
+        }
+    }
+
+    fn reset(&mut self) -> term::Result<()> {
+        self.sgr(0)
+    }
+
+    fn supports_attr(&self, _: term::Attr) -> bool {
+        true
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use term;
+
+    use output::Terminal;
+    use super::AnsiTerminal;
+
+    #[test]
+    fn ansi_terminal() {
+        let mut t = AnsiTerminal(Vec::new());
+        t.fg(1).unwrap();
+        t.bg(12).unwrap();
+        t.fg(208).unwrap();
+        t.attr(term::Attr::Bold).unwrap();
+        t.attr(term::Attr::Italic(false)).unwrap();
+        t.attr(term::Attr::BackgroundColor(0)).unwrap();
+        t.reset().unwrap();
+
+        assert_eq!(
+            String::from_utf8(t.0).unwrap(),
+            "\x1b[31m\x1b[104m\x1b[38;5;208m\x1b[1m\x1b[23m\x1b[40m\x1b[0m",
+        );
+    }
+}
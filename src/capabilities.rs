@@ -0,0 +1,322 @@
+//! Detecting what the terminal behind an output stream supports.
+
+use std::env;
+
+use term;
+use term::terminfo::TermInfo;
+
+use output::{self, Terminal};
+use {Attr, Color, Target};
+
+/// What the terminal behind an output stream supports. This is detected once
+/// per stream from terminfo and the environment, so it's cheap to query:
+///
+/// ```
+/// # use term_painter::{Attr, Capabilities, Color, Target, ToStyle};
+/// let caps = Capabilities::of(Target::Stdout);
+/// let style = if caps.supports(Attr::Italic) {
+///     Attr::Italic.to_style()
+/// } else {
+///     Color::Cyan.to_style()
+/// };
+/// println!("{}", style.paint("emphasized"));
+/// ```
+///
+/// Querying `term` directly is not needed (and using it to change the
+/// terminal state confuses this crate).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    colors: u32,
+    // One bit per `Attr`, indexed by its discriminant
+    attrs: u16,
+    hyperlinks: bool,
+    underline_styles: bool,
+    is_tty: bool,
+}
+
+impl Capabilities {
+    /// Returns the capabilities of the given output stream.
+    pub fn of(target: Target) -> Capabilities {
+        output::with_output(target, |o| o.caps)
+    }
+
+    /// Returns the number of colors the terminal supports. Truecolor terminals
+    /// report `1 << 24` colors, terminals without colors `0`. Like the other
+    /// capabilities, this is detected from the environment (e.g. `TERM`)
+    /// even if the stream is not a terminal, so that the colors fit the
+    /// terminal in case styling is forced via `ColorChoice::Always`.
+    pub fn colors(&self) -> u32 {
+        self.colors
+    }
+
+    /// Returns whether the terminal supports 24-bit RGB colors. There is no
+    /// terminfo capability for that, so this is detected via the `COLORTERM`
+    /// environment variable (`truecolor` or `24bit`).
+    pub fn truecolor(&self) -> bool {
+        self.colors >= 1 << 24
+    }
+
+    /// Returns whether the terminal can display the color without
+    /// downgrading it (see `Color::downgrade`).
+    pub fn supports_color(&self, color: Color) -> bool {
+        color.downgrade(self.colors) == color
+    }
+
+    /// Returns whether the terminal supports the attribute. `Plain` is always
+    /// supported.
+    ///
+    /// Strikethrough and overline have no terminfo capability. They are
+    /// assumed to be supported if italic is, which holds for most modern
+    /// terminals.
+    pub fn supports(&self, attr: Attr) -> bool {
+        attr == Attr::Plain || self.attrs & (1 << attr as u16) != 0
+    }
+
+    /// Returns whether the terminal (probably) supports hyperlinks via
+    /// `Painted::link`. This can't be queried, so it's guessed from
+    /// environment variables; `FORCE_HYPERLINK` overrides the guess.
+    pub fn hyperlinks(&self) -> bool {
+        self.hyperlinks
+    }
+
+    /// Returns whether the terminal supports underline styles (see
+    /// `UnderlineStyle`) and underline colors. `term` doesn't read the
+    /// extended terminfo capabilities for them, so this is guessed from
+    /// environment variables, like hyperlink support. With the ANSI backend,
+    /// they are assumed to be supported.
+    pub fn underline_styles(&self) -> bool {
+        self.underline_styles
+    }
+
+    /// Returns whether the output stream is connected to a terminal.
+    pub fn is_tty(&self) -> bool {
+        self.is_tty
+    }
+}
+
+#[cfg(test)]
+impl Capabilities {
+    /// Capabilities of a terminal with `colors` colors that supports every
+    /// attribute, hyperlinks and underline styles.
+    pub fn with_colors(colors: u32) -> Capabilities {
+        Capabilities { colors, attrs: !0, hyperlinks: true, underline_styles: true, is_tty: true }
+    }
+
+    pub fn no_hyperlinks(self) -> Capabilities {
+        Capabilities { hyperlinks: false, ..self }
+    }
+}
+
+/// Detects the capabilities of the terminal `term` (if any). `ansi` says
+/// whether `term` writes ANSI escape sequences without consulting terminfo.
+pub fn detect(term: Option<&dyn Terminal>, is_tty: bool, ansi: bool) -> Capabilities {
+    let t = match term {
+        None => {
+            return Capabilities {
+                colors: 0,
+                attrs: 0,
+                hyperlinks: false,
+                underline_styles: false,
+                is_tty,
+            }
+        }
+        Some(t) => t,
+    };
+    let info = TermInfo::from_env().ok();
+
+    let mut attrs = 0;
+    for &(attr, term_attr) in &[
+        (Attr::Bold, term::Attr::Bold),
+        (Attr::Dim, term::Attr::Dim),
+        (Attr::Underline, term::Attr::Underline(true)),
+        (Attr::Blink, term::Attr::Blink),
+        (Attr::Reverse, term::Attr::Reverse),
+        (Attr::Secure, term::Attr::Secure),
+        (Attr::Italic, term::Attr::Italic(true)),
+    ] {
+        if t.supports_attr(term_attr) {
+            attrs |= 1 << attr as u16;
+        }
+    }
+    if t.supports_attr(term::Attr::Italic(true)) {
+        attrs |= 1 << Attr::Strikethrough as u16 | 1 << Attr::Overline as u16;
+    }
+
+    Capabilities {
+        colors: detect_colors(info.as_ref(), ansi),
+        attrs,
+        hyperlinks: detect_hyperlinks(),
+        underline_styles: ansi || underline_styles_from_env(|name| env::var(name).ok()),
+        is_tty,
+    }
+}
+
+/// Returns the number of colors the terminal supports. Truecolor support is
+/// announced via the `COLORTERM` variable, as there is no standard terminfo
+/// capability for it. Otherwise, see `terminfo_colors`.
+fn detect_colors(info: Option<&TermInfo>, ansi: bool) -> u32 {
+    match env::var("COLORTERM") {
+        Ok(ref v) if v == "truecolor" || v == "24bit" => 1 << 24,
+        _ => terminfo_colors(info, ansi),
+    }
+}
+
+/// Returns terminfo's `colors` capability, which is missing for terminals
+/// without colors (like `TERM=dumb`). Without terminfo, `term` uses the
+/// Windows console and the ANSI backend is used for ANSI-aware terminals and
+/// logs, which both support at least the 16 standard colors. The ANSI
+/// backend can also be selected explicitly, so it always gets those.
+fn terminfo_colors(info: Option<&TermInfo>, ansi: bool) -> u32 {
+    let colors = info.map(|info| info.numbers.get("colors").cloned().unwrap_or(0));
+    match (colors, ansi) {
+        (Some(n), false) => n,
+        (Some(n), true) => n.max(16),
+        (None, _) => 16,
+    }
+}
+
+/// Guesses whether the terminal supports hyperlinks (OSC 8). There is no
+/// way to query that, so we look for environment variables set by terminals
+/// that are known to support them.
+pub fn detect_hyperlinks() -> bool {
+    hyperlinks_from_env(|name| env::var(name).ok())
+}
+
+fn hyperlinks_from_env<F>(var: F) -> bool
+    where F: Fn(&str) -> Option<String>
+{
+    if let Some(v) = var("FORCE_HYPERLINK") {
+        return v != "0";
+    }
+
+    // VTE based terminals (e.g. GNOME Terminal) support them since 0.50
+    if var("VTE_VERSION").and_then(|v| v.parse::<u32>().ok()).is_some_and(|v| v >= 5000) {
+        return true;
+    }
+
+    if let Some(p) = var("TERM_PROGRAM") {
+        if ["iTerm.app", "WezTerm", "vscode", "ghostty", "Hyper"].contains(&&*p) {
+            return true;
+        }
+    }
+
+    if let Some(t) = var("TERM") {
+        if ["xterm-kitty", "alacritty", "foot", "xterm-ghostty"].contains(&&*t) {
+            return true;
+        }
+    }
+
+    ["WT_SESSION", "KONSOLE_VERSION", "DOMTERM"].iter().any(|name| var(name).is_some())
+}
+
+/// Guesses whether the terminal supports underline styles and colors (the
+/// extended terminfo capabilities `Smulx` and `Setulc`) from environment
+/// variables set by terminals that are known to support them.
+fn underline_styles_from_env<F>(var: F) -> bool
+    where F: Fn(&str) -> Option<String>
+{
+    let term = var("TERM").unwrap_or_default();
+    // Multiplexers (running inside such a terminal) drop them by default
+    if term.starts_with("screen") || term.starts_with("tmux") {
+        return false;
+    }
+
+    // VTE based terminals support them since 0.51.2
+    if var("VTE_VERSION").and_then(|v| v.parse::<u32>().ok()).is_some_and(|v| v >= 5102) {
+        return true;
+    }
+
+    if let Some(p) = var("TERM_PROGRAM") {
+        if ["iTerm.app", "WezTerm", "ghostty"].contains(&&*p) {
+            return true;
+        }
+    }
+
+    ["xterm-kitty", "xterm-ghostty", "wezterm", "foot", "alacritty", "contour"].contains(&&*term)
+}
+
+
+#[cfg(test)]
+mod test {
+    use Attr::*;
+    use Color::*;
+    use std::collections::HashMap;
+    use term::terminfo::TermInfo;
+    use test_util::env;
+    use super::{hyperlinks_from_env, terminfo_colors, underline_styles_from_env, Capabilities};
+
+    fn eval(vars: &[(&str, &str)]) -> bool {
+        hyperlinks_from_env(env(vars))
+    }
+
+    #[test]
+    fn hyperlink_detection() {
+        assert!(!eval(&[]));
+        assert!(!eval(&[("TERM", "xterm-256color")]));
+        assert!(!eval(&[("VTE_VERSION", "4800")]));
+        assert!(eval(&[("VTE_VERSION", "6003")]));
+        assert!(eval(&[("TERM_PROGRAM", "WezTerm")]));
+        assert!(eval(&[("TERM", "xterm-kitty")]));
+        assert!(eval(&[("WT_SESSION", "abc")]));
+        assert!(eval(&[("FORCE_HYPERLINK", "1")]));
+        assert!(!eval(&[("FORCE_HYPERLINK", "0"), ("WT_SESSION", "abc")]));
+    }
+
+    #[test]
+    fn underline_style_detection() {
+        let eval = |vars| underline_styles_from_env(env(vars));
+        assert!(!eval(&[]));
+        assert!(!eval(&[("TERM", "xterm-256color")]));
+        assert!(eval(&[("TERM", "xterm-kitty")]));
+        assert!(eval(&[("TERM", "xterm-256color"), ("VTE_VERSION", "6003")]));
+        assert!(!eval(&[("TERM", "xterm-256color"), ("VTE_VERSION", "5000")]));
+        assert!(eval(&[("TERM_PROGRAM", "WezTerm")]));
+        assert!(!eval(&[("TERM", "screen"), ("VTE_VERSION", "6003")]));
+    }
+
+    #[test]
+    fn color_detection() {
+        let info = |colors: Option<u32>| TermInfo {
+            names: vec!["test".to_string()],
+            bools: HashMap::new(),
+            numbers: colors.into_iter().map(|n| ("colors", n)).collect(),
+            strings: HashMap::new(),
+        };
+
+        assert_eq!(terminfo_colors(Some(&info(Some(256))), false), 256);
+        assert_eq!(terminfo_colors(Some(&info(Some(8))), false), 8);
+        assert_eq!(terminfo_colors(Some(&info(Some(8))), true), 16);
+        assert_eq!(terminfo_colors(Some(&info(None)), false), 0);
+        assert_eq!(terminfo_colors(None, false), 16);
+        assert_eq!(terminfo_colors(None, true), 16);
+    }
+
+    #[test]
+    fn queries() {
+        let caps = Capabilities {
+            colors: 256,
+            attrs: 1 << Bold as u16 | 1 << Underline as u16,
+            hyperlinks: false,
+            underline_styles: false,
+            is_tty: true,
+        };
+
+        assert!(!caps.truecolor());
+        assert!(caps.supports_color(Custom(208)));
+        assert!(!caps.supports_color(Rgb(1, 2, 3)));
+        assert!(caps.supports(Plain));
+        assert!(caps.supports(Bold));
+        assert!(!caps.supports(Italic));
+
+        let none = Capabilities {
+            colors: 0,
+            attrs: 0,
+            hyperlinks: false,
+            underline_styles: false,
+            is_tty: false,
+        };
+        assert!(!none.supports_color(Red));
+        assert!(none.supports_color(NotSet));
+        assert!(!none.supports(Bold));
+    }
+}
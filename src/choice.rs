@@ -0,0 +1,160 @@
+//! Deciding whether styles are applied at all.
+
+use std::cell::Cell;
+use std::env;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Decides whether styles are applied at all.
+///
+/// The process-wide choice is `Auto` by default. It is consulted every time
+/// a style is applied, both on the terminal and by `Painted::ansi`. Use
+/// `set_color_choice` to change it, e.g. for a `--color` command line flag,
+/// or `ColorChoice::with` to override it temporarily:
+///
+/// ```
+/// # use term_painter::{Color, ColorChoice, ToStyle};
+/// ColorChoice::Never.with(|| {
+///     assert_eq!(Color::Red.paint("plain").ansi().to_string(), "plain");
+/// });
+/// ```
+///
+/// Versions up to 0.3 applied styles even if the output was not a terminal.
+/// Use `set_color_choice(ColorChoice::Always)` to keep that behavior.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Always apply styles, even if the output is not a terminal.
+    Always,
+    /// Never apply styles.
+    Never,
+    /// Apply styles if the output is a terminal, unless the environment says
+    /// otherwise:
+    ///
+    /// - `NO_COLOR` set to a non-empty value disables styling,
+    /// - `CLICOLOR_FORCE` set to anything but `0` enables styling, even if the
+    ///   output is not a terminal,
+    /// - `CLICOLOR` set to `0` disables styling.
+    ///
+    /// As it's unknown where the strings created by `Painted::ansi` end up,
+    /// only the environment is consulted for those.
+    Auto,
+}
+
+impl ColorChoice {
+    fn from_usize(v: usize) -> Self {
+        match v {
+            0 => ColorChoice::Always,
+            1 => ColorChoice::Never,
+            _ => ColorChoice::Auto,
+        }
+    }
+
+    /// Executes the given function with `self` as the color choice of the
+    /// current thread and restores the previous choice afterwards. Like
+    /// `ToStyle::with`, calls can be nested.
+    pub fn with<F, R>(self, f: F) -> R
+        where F: FnOnce() -> R
+    {
+        let before = SCOPED_CHOICE.with(|c| c.replace(Some(self)));
+        let out = f();
+        SCOPED_CHOICE.with(|c| c.set(before));
+
+        out
+    }
+
+    /// Returns whether styles should be applied to an output with the given
+    /// TTY status. `None` means that the TTY status is unknown.
+    pub(crate) fn enabled(self, is_tty: Option<bool>) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                env_choice(|name| env::var(name).ok())
+                    .or(is_tty)
+                    .unwrap_or(true)
+            }
+        }
+    }
+}
+
+static COLOR_CHOICE: AtomicUsize = AtomicUsize::new(ColorChoice::Auto as usize);
+
+thread_local!(
+    static SCOPED_CHOICE: Cell<Option<ColorChoice>> = const { Cell::new(None) }
+);
+
+/// Returns the color choice that is currently in effect: the one set by an
+/// enclosing `ColorChoice::with` call or the process-wide one otherwise.
+pub fn color_choice() -> ColorChoice {
+    SCOPED_CHOICE.with(|c| c.get())
+        .unwrap_or_else(|| ColorChoice::from_usize(COLOR_CHOICE.load(Ordering::Relaxed)))
+}
+
+/// Sets the process-wide color choice. `Never` turns printing a `Painted`
+/// and `ToStyle::with` into no-ops style-wise, `Always` applies styles even
+/// if the output is not a terminal.
+///
+/// ```
+/// # use term_painter::{set_color_choice, ColorChoice};
+/// // e.g. for `--color=never`
+/// set_color_choice(ColorChoice::Never);
+/// ```
+pub fn set_color_choice(choice: ColorChoice) {
+    COLOR_CHOICE.store(choice as usize, Ordering::Relaxed);
+}
+
+/// Evaluates the `NO_COLOR`, `CLICOLOR_FORCE` and `CLICOLOR` environment
+/// variables. Returns `None` if they don't enforce any decision.
+fn env_choice<F>(var: F) -> Option<bool>
+    where F: Fn(&str) -> Option<String>
+{
+    if var("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+        return Some(false);
+    }
+    if var("CLICOLOR_FORCE").is_some_and(|v| v != "0") {
+        return Some(true);
+    }
+    if var("CLICOLOR").is_some_and(|v| v == "0") {
+        return Some(false);
+    }
+
+    None
+}
+
+
+#[cfg(test)]
+mod test {
+    use test_util::env;
+    use super::{env_choice, color_choice, ColorChoice};
+
+    fn eval(vars: &[(&str, &str)]) -> Option<bool> {
+        env_choice(env(vars))
+    }
+
+    #[test]
+    fn env_conventions() {
+        assert_eq!(eval(&[]), None);
+        assert_eq!(eval(&[("NO_COLOR", "1")]), Some(false));
+        assert_eq!(eval(&[("NO_COLOR", "")]), None);
+        assert_eq!(eval(&[("CLICOLOR_FORCE", "1")]), Some(true));
+        assert_eq!(eval(&[("CLICOLOR_FORCE", "0")]), None);
+        assert_eq!(eval(&[("CLICOLOR", "0")]), Some(false));
+        assert_eq!(eval(&[("CLICOLOR", "1")]), None);
+
+        // `NO_COLOR` wins
+        assert_eq!(eval(&[("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1")]), Some(false));
+        assert_eq!(eval(&[("CLICOLOR", "0"), ("CLICOLOR_FORCE", "1")]), Some(true));
+    }
+
+    #[test]
+    fn scoped_choice() {
+        let global = color_choice();
+        ColorChoice::Never.with(|| {
+            assert_eq!(color_choice(), ColorChoice::Never);
+            ColorChoice::Always.with(|| {
+                assert_eq!(color_choice(), ColorChoice::Always);
+            });
+            assert_eq!(color_choice(), ColorChoice::Never);
+        });
+        assert_eq!(color_choice(), global);
+    }
+}
@@ -0,0 +1,97 @@
+//! The error type of the fallible API.
+
+use std::error;
+use std::fmt;
+use std::io;
+
+use term;
+
+use {Attr, Color, Target};
+
+/// Error returned when applying a style fails, e.g. by `ToStyle::try_with`
+/// or `Painted::try_write_to`.
+///
+/// Note that it's not an error if styling is disabled by the `ColorChoice`,
+/// e.g. because the output is not a terminal.
+#[derive(Debug)]
+pub enum Error {
+    /// Styling is enabled, but the output stream is not a terminal or there
+    /// is no terminfo entry for it.
+    NoTerminal(Target),
+    /// The terminal doesn't support the attribute.
+    UnsupportedAttr(Attr),
+    /// The terminal doesn't support the color.
+    UnsupportedColor(Color),
+    /// Writing to the output stream failed. Other errors from reading the
+    /// terminfo database are reported as this, too.
+    Io(io::Error),
+}
+
+impl Error {
+    /// Converts an error of `term`. `NotSupported` errors are replaced by
+    /// `unsupported`, which should say what exactly isn't supported.
+    pub(crate) fn from_term(e: term::Error, unsupported: Error) -> Error {
+        match e {
+            term::Error::NotSupported | term::Error::ColorOutOfRange => unsupported,
+            term::Error::Io(e) => Error::Io(e),
+            e => Error::Io(io::Error::new(io::ErrorKind::Other, e)),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::NoTerminal(target) => write!(f, "{:?} is not a terminal", target),
+            Error::UnsupportedAttr(attr) => {
+                write!(f, "attribute `{}` is not supported by the terminal", attr)
+            }
+            Error::UnsupportedColor(color) => {
+                write!(f, "color `{}` is not supported by the terminal", color)
+            }
+            Error::Io(ref e) => write!(f, "failed to write to the terminal: {}", e),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use term;
+
+    use {Attr, Color, Target};
+    use super::Error;
+
+    #[test]
+    fn from_term() {
+        let e = Error::from_term(term::Error::NotSupported, Error::UnsupportedAttr(Attr::Italic));
+        assert_eq!(e.to_string(), "attribute `italic` is not supported by the terminal");
+
+        let e = Error::from_term(term::Error::ColorOutOfRange, Error::UnsupportedColor(Color::Custom(300)));
+        assert_eq!(e.to_string(), "color `300` is not supported by the terminal");
+
+        let io = io::Error::new(io::ErrorKind::BrokenPipe, "pipe");
+        match Error::from_term(term::Error::Io(io), Error::NoTerminal(Target::Stdout)) {
+            Error::Io(ref e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+            other => panic!("unexpected error: {:?}", other),
+        }
+        assert_eq!(Error::NoTerminal(Target::Stderr).to_string(), "Stderr is not a terminal");
+    }
+}
@@ -139,6 +139,40 @@
 //! }
 //! ```
 //!
+//! Styles can also be parsed from strings, e.g. to let users configure
+//! colors in a config file. See `Style`'s `FromStr` implementation for the
+//! exact grammar.
+//!
+//! ```
+//! # use term_painter::{Style, ToStyle};
+//! let style: Style = "bold bright-red on #1e1e1e".parse().unwrap();
+//! println!("{}", style.paint("Error"));
+//! ```
+//!
+//! For styles that are fixed at compile time, the `style!` macro accepts the
+//! same words, reports mistakes as compile errors and can be used in
+//! constants: `const ERROR: Style = style!(bold bright-red);`. The `const`
+//! builder methods of `Style` work there, too:
+//! `const ERROR: Style = Style::new().fg(Color::BrightRed).bold();`.
+//!
+//! To configure the styles of an application in one place, use a `Theme`: it
+//! maps names like `"error"` to styles and can be loaded from a file.
+//!
+//! Text made of several differently styled parts is a `StyledText`. It can
+//! also be written as markup with `parse_markup` or `paint_markup!`:
+//!
+//! ```
+//! # #[macro_use] extern crate term_painter;
+//! # fn main() {
+//! let file = "main.rs";
+//! println!("{}", paint_markup!("[bold red]error[/]: [underline]{}[/] not found", file));
+//! # }
+//! ```
+//!
+//! With the `serde` feature enabled, `Style`, `Color` and `Attr` implement
+//! `Serialize` and `Deserialize`, so that they can be stored in config files
+//! (see the `serde` module for the format).
+//!
 //! Some Notes
 //! ----------
 //! If you don't want to pollute your namespace with `Color` and `Attr` names,
@@ -150,12 +184,23 @@
 //!
 //! Please note that global state is changed when printing a `Painted`
 //! object. This means that some state is set before and reset after printing.
-//! This means that, for example, using this library in `format!` or `write!`
-//! won't work. The color formatting is not stored in the resulting string.
-//! Although Unix terminals do modify color and formatting by printing special
-//! control characters, Windows and others do not. This library uses the
-//! plattform independent library `term`, thus saving formatted text in a
-//! string not possible. This was a design choice.
+//! By default, the style is therefore not stored in the string when a
+//! `Painted` is used with `format!` or `write!`: although Unix terminals do
+//! modify color and formatting by printing special control characters,
+//! Windows and others do not, and this library uses the platform independent
+//! library `term` to support all of them.
+//!
+//! If you know that your output ends up in an ANSI terminal anyway, call
+//! `ansi()` on a `Painted` object. It then writes ANSI escape sequences into
+//! the formatter directly, so the style is kept by `format!`, `write!` and
+//! `to_string()`:
+//!
+//! ```
+//! # use term_painter::ToStyle;
+//! # use term_painter::Color::*;
+//! let s = Red.bold().paint("error").ansi().to_string();
+//! assert_eq!(s, "\x1b[31;1merror\x1b[0m");
+//! ```
 //!
 //! This crate also assumes that the terminal state is not altered by anything
 //! else. Calling `term` function directly might result in strange behaviour.
@@ -164,22 +209,83 @@
 //! itself. However, there shouldn't be any problems when the terminal state
 //! is completely reset in between using those two different methods.
 //!
+//! Styles are applied to stdout by default. If you print to stderr, you have
+//! to tell this crate via `ToStyle::with_target` or `Painted::target`,
+//! otherwise the style is applied to the wrong stream.
+//!
 //! Another possible source of confusion might be multithreading. Terminal
 //! state and handles are hold in thread local variables. If two terminal
 //! handles would reference the same physical terminal, those two threads could
 //! interfere with each other. I have not tested it, though. Usually, you don't
 //! want to print to the same Terminal in two threads simultanously anyway.
 //!
+//! Whether styles are applied at all is decided by the `ColorChoice`. By
+//! default, styles are only applied if the output is a terminal and the
+//! environment doesn't say otherwise (via `NO_COLOR`, `CLICOLOR` or
+//! `CLICOLOR_FORCE`). This can be overridden with `set_color_choice` and
+//! `ColorChoice::with`.
+//!
 //! Functions of `term` sometimes return a `Result` that is `Err` when the
-//! function fails to set the state. However, this crate silently ignores those
-//! failures. To check the capabilities of the terminal, use `term` directly.
+//! function fails to set the state. `with` and printing a `Painted` silently
+//! ignore those failures. If you want to know why styling failed, use the
+//! fallible versions `ToStyle::try_with` and `Painted::try_write_to`, which
+//! return an `Error`.
+//!
+//! To make styling decisions based on what the terminal supports (colors,
+//! attributes, hyperlinks), use `Capabilities::of` instead of `term`.
+//!
+//! If `term` can't find a terminal (e.g. because `TERM` is not set or there
+//! is no terminfo database, as in many containers), ANSI escape sequences are
+//! written directly. See `Backend` and `set_backend` to change that.
 //!
 
 extern crate term;
+extern crate unicode_width;
+#[cfg(feature = "serde")]
+extern crate serde as serde_crate;
 
 use std::default::Default;
 use std::fmt;
-use std::cell::RefCell;
+use std::io::{self, Write};
+use std::cell::Cell;
+
+mod ansi;
+mod backend;
+mod capabilities;
+mod choice;
+mod error;
+mod markup;
+mod output;
+mod palette;
+mod parse;
+#[cfg(feature = "serde")]
+pub mod serde;
+mod spans;
+mod strip;
+mod style_macro;
+#[cfg(test)]
+mod test_util;
+mod text;
+mod theme;
+mod width;
+
+pub use ansi::Transition;
+pub use backend::{backend, set_backend, Backend};
+pub use capabilities::Capabilities;
+pub use choice::{color_choice, set_color_choice, ColorChoice};
+pub use error::Error;
+pub use markup::{parse_markup, MarkupError};
+pub use output::{pop_style, push_style, try_pop_style, try_push_style, Target};
+pub use parse::ParseError;
+pub use spans::{parse_ansi, AnsiSpans};
+pub use strip::{strip_ansi, StripWriter};
+pub use text::StyledText;
+pub use theme::{Theme, ThemeError, ThemeIter};
+pub use width::visible_width;
+#[doc(hidden)]
+pub use markup::{check_markup_format, MarkupArgs};
+#[doc(hidden)]
+pub use style_macro::{parse_hex_const, parse_literal_const};
 
 
 /// Everything that can be seen as part of a style. This is the core of this
@@ -237,11 +343,43 @@ pub trait ToStyle : Sized {
         self.to_mapped_style(|s| s.set_reverse(Some(true)))
     }
 
-    /// Secure mode.
+    /// Secure mode, also called conceal or hidden mode: the text is not
+    /// visible.
     fn secure(self) -> Style {
         self.to_mapped_style(|s| s.set_secure(Some(true)))
     }
 
+    /// Underlines the text with the given style (e.g. a curly line). Not
+    /// supported by all terminals; others usually fall back to a straight
+    /// line.
+    fn underline_style(self, u: UnderlineStyle) -> Style {
+        self.to_mapped_style(|s| {
+            s.set_underline(Some(true));
+            s.set_underline_style(Some(u));
+        })
+    }
+
+    /// Sets the color of the underline. By default, the underline has the
+    /// same color as the text. Not supported by all terminals.
+    fn underline_color(self, c: Color) -> Style {
+        self.to_mapped_style(|s| s.underline_color = c)
+    }
+
+    /// Italic text. Not supported by all terminals.
+    fn italic(self) -> Style {
+        self.to_mapped_style(|s| s.set_italic(Some(true)))
+    }
+
+    /// Crosses out the text. Not supported by all terminals.
+    fn strikethrough(self) -> Style {
+        self.to_mapped_style(|s| s.set_strikethrough(Some(true)))
+    }
+
+    /// Draws a line above the text. Not supported by all terminals.
+    fn overline(self) -> Style {
+        self.to_mapped_style(|s| s.set_overline(Some(true)))
+    }
+
     /// Wraps the style specified in `self` and something of arbitrary type
     /// into a `Painted`. When `Painted` is printed it will print the arbitrary
     /// something with the given style.
@@ -250,32 +388,73 @@ pub trait ToStyle : Sized {
     {
         Painted {
             style: self.clone().to_style(),
-            obj: obj,
+            obj,
+            ansi: false,
+            target: Target::Stdout,
+            link: None,
         }
     }
 
     /// Executes the given function, applying the style information before
-    /// calling it and resetting after it finished.
+    /// calling it and resetting after it finished. The style is applied to
+    /// stdout; use `with_target` to style another output stream.
     fn with<F, R>(&self, f: F) -> R
         where F: FnOnce() -> R,
               Self: Clone
     {
-        // Shorthand for the new style and the style that was active before
-        let new = self.clone().to_style();
-        let before = CURR_STYLE.with(|curr| curr.borrow().clone());
-
-        // Apply the new style and setting the merged style as CURR_STYLE
-        let _ = new.apply();
-        CURR_STYLE.with(|curr| *curr.borrow_mut() = before.and(new));
+        self.with_target(Target::Stdout, f)
+    }
 
+    /// Like `with`, but applies the style to the given output stream.
+    /// This is a shorthand for `push_style` and `pop_style` around `f`.
+    ///
+    /// ```
+    /// # use term_painter::{Color, Target, ToStyle};
+    /// Color::Red.with_target(Target::Stderr, || {
+    ///     eprintln!("Something went wrong");
+    /// });
+    /// ```
+    fn with_target<F, R>(&self, target: Target, f: F) -> R
+        where F: FnOnce() -> R,
+              Self: Clone
+    {
+        output::push_style(target, self.clone());
         let out = f();
-
-        // Revert to the style that was active before and set it as current
-        let _ = before.revert_to();
-        CURR_STYLE.with(|curr| *curr.borrow_mut() = before);
+        output::pop_style(target);
 
         out
     }
+
+    /// Like `with`, but returns an error if the style can't be applied or
+    /// the previous style can't be restored. If applying the style fails, `f`
+    /// is not called. Styling being disabled by the `ColorChoice` is not an
+    /// error.
+    ///
+    /// ```
+    /// # use term_painter::{Attr, ToStyle};
+    /// let res = Attr::Italic.try_with(|| println!("maybe italic"));
+    /// if let Err(e) = res {
+    ///     eprintln!("styling failed: {}", e);
+    /// }
+    /// ```
+    fn try_with<F, R>(&self, f: F) -> Result<R, Error>
+        where F: FnOnce() -> R,
+              Self: Clone
+    {
+        self.try_with_target(Target::Stdout, f)
+    }
+
+    /// Like `with_target`, but returns an error like `try_with`.
+    fn try_with_target<F, R>(&self, target: Target, f: F) -> Result<R, Error>
+        where F: FnOnce() -> R,
+              Self: Clone
+    {
+        output::try_push_style(target, self.clone())?;
+        let out = f();
+        output::try_pop_style(target)?;
+
+        Ok(out)
+    }
 }
 
 /// Lists all possible Colors. It implements `ToStyle` so it's possible to call
@@ -288,13 +467,16 @@ pub trait ToStyle : Sized {
 /// ```
 ///
 /// It is not guaranteed that the local terminal supports all of those colors.
-/// As already mentioned in the module documentation, you should use `term`
-/// directly to check the terminal's capabilities.
+/// When a style is applied to the terminal, colors are mapped to the closest
+/// color the terminal can display (see `Color::downgrade`). The number of
+/// colors is taken from terminfo or, for truecolor, the `COLORTERM`
+/// environment variable.
 ///
 /// **Note**: Using `Color::NotSet` will *not* reset the color to the default
 /// terminal color.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
 pub enum Color {
+    #[default]
     NotSet,
     Black,
     Red,
@@ -312,14 +494,19 @@ pub enum Color {
     BrightMagenta,
     BrightCyan,
     BrightWhite,
+    /// A color from the terminal's palette, addressed by its index.
     Custom(u32),
+    /// A 24-bit "truecolor" given by its red, green and blue components.
+    Rgb(u8, u8, u8),
 }
 
 impl Color {
-    /// Returns the associated constant from `term::color::Color`.
+    /// Returns the associated constant from `term::color::Color`. `term`
+    /// does not know about RGB colors, so `None` is returned for those, too.
     fn term_constant(&self) -> Option<term::color::Color> {
         match *self {
             Color::NotSet        => None,
+            Color::Rgb(..)       => None,
             Color::Black         => Some(term::color::BLACK),
             Color::Red           => Some(term::color::RED),
             Color::Green         => Some(term::color::GREEN),
@@ -339,11 +526,74 @@ impl Color {
             Color::Custom(c)     => Some(c)
         }
     }
-}
 
-impl Default for Color {
-    fn default() -> Self {
-        Color::NotSet
+    /// Returns the color with the given palette index, preferring named
+    /// colors for the 16 standard colors.
+    fn from_index(idx: u32) -> Color {
+        match idx {
+            0  => Color::Black,
+            1  => Color::Red,
+            2  => Color::Green,
+            3  => Color::Yellow,
+            4  => Color::Blue,
+            5  => Color::Magenta,
+            6  => Color::Cyan,
+            7  => Color::White,
+            8  => Color::BrightBlack,
+            9  => Color::BrightRed,
+            10 => Color::BrightGreen,
+            11 => Color::BrightYellow,
+            12 => Color::BrightBlue,
+            13 => Color::BrightMagenta,
+            14 => Color::BrightCyan,
+            15 => Color::BrightWhite,
+            _  => Color::Custom(idx),
+        }
+    }
+
+    /// Maps this color to the closest color a terminal with `colors` colors
+    /// can display. Truecolor terminals report `1 << 24` colors.
+    ///
+    /// RGB colors are mapped to the 256 color palette, palette colors beyond
+    /// the first 16 are mapped to the 16 standard colors and bright colors
+    /// are mapped to their normal counterpart on 8 color terminals. If the
+    /// terminal doesn't support colors at all, `NotSet` is returned.
+    ///
+    /// ```
+    /// # use term_painter::Color;
+    /// assert_eq!(Color::Rgb(250, 10, 10).downgrade(256), Color::Custom(196));
+    /// assert_eq!(Color::Rgb(250, 10, 10).downgrade(16), Color::BrightRed);
+    /// assert_eq!(Color::Rgb(250, 10, 10).downgrade(8), Color::Red);
+    /// ```
+    pub fn downgrade(self, colors: u32) -> Color {
+        if colors < 8 {
+            return Color::NotSet;
+        }
+
+        match self {
+            Color::Rgb(r, g, b) if colors < 1 << 24 => {
+                Color::Custom(palette::nearest_256((r, g, b)) as u32).downgrade(colors)
+            }
+            Color::Custom(c) if c >= colors && c >= 16 => {
+                // Indices beyond 255 are not part of the standard palette, so
+                // there is nothing sensible to map them to.
+                if c > 255 {
+                    return Color::NotSet;
+                }
+                let rgb = palette::rgb_of_index(c as u8);
+                Color::from_index(palette::nearest_16(rgb) as u32).downgrade(colors)
+            }
+            Color::Custom(c) if c >= colors && c >= 8 => Color::Custom(c - 8),
+            Color::BrightBlack   if colors < 16 => Color::Black,
+            Color::BrightRed     if colors < 16 => Color::Red,
+            Color::BrightGreen   if colors < 16 => Color::Green,
+            Color::BrightYellow  if colors < 16 => Color::Yellow,
+            Color::BrightBlue    if colors < 16 => Color::Blue,
+            Color::BrightMagenta if colors < 16 => Color::Magenta,
+            Color::BrightCyan    if colors < 16 => Color::Cyan,
+            Color::BrightWhite   if colors < 16 => Color::White,
+            c => c,
+        }
     }
 }
 
@@ -368,8 +618,8 @@ impl ToStyle for Color {
 /// ```
 ///
 /// It is not guaranteed that the local terminal supports all of those
-/// formatting options. As already mentioned in the module documentation, you
-/// should use `term` directly to check the terminal's capabilities.
+/// formatting options. Use `Capabilities::supports` to check what the
+/// terminal supports.
 ///
 /// For more information about enum variants, see `term::Attr` Documentation.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -381,63 +631,72 @@ pub enum Attr {
     Underline,
     Blink,
     Reverse,
+    /// Also known as conceal or hidden
     Secure,
+    Italic,
+    Strikethrough,
+    Overline,
 }
 
 impl ToStyle for Attr {
     /// Returns a Style with default values and the `self` attribute enabled.
     fn to_style(self) -> Style {
         let mut s = Style::default();
-        match self {
-            Attr::Plain => {},
-            Attr::Bold => s.set_bold(Some(true)),
-            Attr::Dim => s.set_dim(Some(true)),
-            Attr::Underline => s.set_underline(Some(true)),
-            Attr::Blink => s.set_blink(Some(true)),
-            Attr::Reverse => s.set_reverse(Some(true)),
-            Attr::Secure => s.set_secure(Some(true)),
-        }
+        s.set_attr(self, Some(true));
         s
     }
 }
 
+/// The different kinds of underlines. Those are an extension of the ANSI
+/// standard supported by some terminals (e.g. kitty, WezTerm and VTE based
+/// ones). Use it with `ToStyle::underline_style`:
+///
+/// ```
+/// # use term_painter::{Color, ToStyle, UnderlineStyle};
+/// let error = Color::Red.underline_style(UnderlineStyle::Curly);
+/// println!("{}", error.paint("mispeled"));
+/// ```
+///
+/// They (and the underline color) are only sent to terminals that support
+/// them according to `Capabilities::underline_styles`. Otherwise, a plain
+/// underline is used. `Painted::ansi` always writes them.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UnderlineStyle {
+    Single,
+    Double,
+    Curly,
+    Dotted,
+    Dashed,
+}
+
 /// Saves all properties of a style. Implements `ToStyle`, so you can call
 /// style modifiers on it.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
 pub struct Style {
     pub fg: Color,
     pub bg: Color,
+    pub underline_color: Color,
+    underline_style: Option<UnderlineStyle>,
     // Each attribute was `Option<bool>` once. To reduce struct size, the
     // Option type is simulated with 2 bits for each attribute. The first
     // attribute in the name uses the MSBs, the last attribute the LSBs.
     // 00 => None, 10 => Some(false), 11 => Some(true)
+    // The lower six bits of `overline` are unused.
     bold_dim_underline_blink: u8,
-    reverse_secure: u8,
+    reverse_secure_italic_strikethrough: u8,
+    overline: u8,
 }
 
-
-impl Default for Style {
-    fn default() -> Self {
-        Style {
-            fg: Color::default(),
-            bg: Color::default(),
-            bold_dim_underline_blink: 0,
-            reverse_secure: 0,
-        }
-    }
-}
-
-thread_local!(
-    static TERM: RefCell<Option<Box<term::StdoutTerminal>>> = RefCell::new(term::stdout())
-);
 thread_local!(
-    static CURR_STYLE: RefCell<Style> = RefCell::new(Style::default())
+    // The style of the innermost `Painted` that is currently written in
+    // ANSI mode or `None` if there is none.
+    static ANSI_STYLE: Cell<Option<Style>> = const { Cell::new(None) }
 );
 
-// Macro to generate getter and setter for all attributes. This hides almost
+// Macro to generate getter and setters for all attributes. This hides almost
 // all bit magic in here.
 macro_rules! gen_getter {
-    ($getter:ident, $setter:ident, $var:ident, $pos:expr) => {
+    ($getter:ident, $setter:ident, $with:ident, $var:ident, $pos:expr) => {
         pub fn $getter(&self) -> Option<bool> {
             // shift important bits to the right and mask them
             match (self.$var >> ($pos * 2)) & 0b11 {
@@ -448,88 +707,158 @@ macro_rules! gen_getter {
         }
 
         pub fn $setter(&mut self, v: Option<bool>) {
-            match v {
-                None => {
-                    // Set important bits to 00
-                    self.$var &= !(0b11 << ($pos*2));
-                },
-                Some(false) => {
-                    // Set important bits to 10
-                    self.$var &= !(0b01 << ($pos*2));
-                    self.$var |= 0b10 << ($pos*2);
-                },
-                Some(true) => {
-                    // Set important bits to 11
-                    self.$var |= 0b11 << ($pos*2);
-                },
-            }
+            *self = self.$with(v);
+        }
+
+        // By value, so that it can be used in `const` contexts.
+        const fn $with(mut self, v: Option<bool>) -> Style {
+            // None => 00, Some(false) => 10, Some(true) => 11
+            let bits = match v {
+                None => 0b00,
+                Some(false) => 0b10,
+                Some(true) => 0b11,
+            };
+            // Clear the important bits, then set them
+            self.$var = (self.$var & !(0b11 << ($pos * 2))) | (bits << ($pos * 2));
+            self
         }
     }
 }
 
 impl Style {
     // Generate a bunch of getters and setters to hide bit fiddling.
-    gen_getter!(get_bold,       set_bold,       bold_dim_underline_blink, 3);
-    gen_getter!(get_dim,        set_dim,        bold_dim_underline_blink, 2);
-    gen_getter!(get_underline,  set_underline,  bold_dim_underline_blink, 1);
-    gen_getter!(get_blink,      set_blink,      bold_dim_underline_blink, 0);
-    gen_getter!(get_reverse,    set_reverse,    reverse_secure, 3);
-    gen_getter!(get_secure,     set_secure,     reverse_secure, 2);
+    gen_getter!(get_bold,       set_bold,       with_bold,      bold_dim_underline_blink, 3);
+    gen_getter!(get_dim,        set_dim,        with_dim,       bold_dim_underline_blink, 2);
+    gen_getter!(get_underline,  set_underline,  with_underline, bold_dim_underline_blink, 1);
+    gen_getter!(get_blink,      set_blink,      with_blink,     bold_dim_underline_blink, 0);
+    gen_getter!(get_reverse,    set_reverse,    with_reverse,   reverse_secure_italic_strikethrough, 3);
+    gen_getter!(get_secure,     set_secure,     with_secure,    reverse_secure_italic_strikethrough, 2);
+    gen_getter!(get_italic,     set_italic,     with_italic,    reverse_secure_italic_strikethrough, 1);
+    gen_getter!(get_strikethrough, set_strikethrough, with_strikethrough,
+        reverse_secure_italic_strikethrough, 0);
+    gen_getter!(get_overline,   set_overline,   with_overline,  overline, 3);
 
+    /// Returns the value of the given attribute. `Plain` is always `None`.
+    fn get_attr(&self, attr: Attr) -> Option<bool> {
+        match attr {
+            Attr::Plain => None,
+            Attr::Bold => self.get_bold(),
+            Attr::Dim => self.get_dim(),
+            Attr::Underline => self.get_underline(),
+            Attr::Blink => self.get_blink(),
+            Attr::Reverse => self.get_reverse(),
+            Attr::Secure => self.get_secure(),
+            Attr::Italic => self.get_italic(),
+            Attr::Strikethrough => self.get_strikethrough(),
+            Attr::Overline => self.get_overline(),
+        }
+    }
+
+    /// Sets the value of the given attribute. Setting `Plain` does nothing.
+    fn set_attr(&mut self, attr: Attr, v: Option<bool>) {
+        *self = self.with_attr(attr, v);
+    }
+
+    /// Returns the underline style. It only takes effect if the underline
+    /// attribute is set, too.
+    pub fn get_underline_style(&self) -> Option<UnderlineStyle> {
+        self.underline_style
+    }
+
+    pub fn set_underline_style(&mut self, u: Option<UnderlineStyle>) {
+        self.underline_style = u;
+    }
 
-    fn apply(&self) -> Result<(), fmt::Error> {
-        // Like `try!`, but converts `term`-Error into `fmt::Error`
+
+    fn apply(&self, target: Target) -> Result<(), Error> {
+        // Like `try!`, but converts `term`-Error into our `Error`, saying
+        // what's unsupported
         macro_rules! try_term {
-            ($e:expr) => { $e.map_err(|_| fmt::Error)? }
+            ($e:expr, $unsupported:expr) => {
+                $e.map_err(|e| Error::from_term(e, $unsupported))?
+            }
         }
 
-        TERM.with(|term_opt| {
-            let mut tmut = term_opt.borrow_mut();
-            let t = match tmut.as_mut() {
-                None => return Err(fmt::Error),
+        output::with_output(target, |o| {
+            if !o.enabled() {
+                return Ok(());
+            }
+            let t = match o.term.as_mut() {
+                None => return Err(Error::NoTerminal(target)),
                 Some(t) => t,
             };
+            let style = self.downgrade(o.caps.colors());
 
-            // Apply colors if set.
-            if let Some(c) = self.fg.term_constant() {
-                try_term!(t.fg(c));
+            // Apply colors if set. RGB colors are not supported by `term`,
+            // so we write the escape sequence ourselves.
+            if let Color::Rgb(..) = style.fg {
+                ansi::write_io(t, &style.fg.to_style())?;
+            } else if let Some(c) = style.fg.term_constant() {
+                try_term!(t.fg(c), Error::UnsupportedColor(style.fg));
             }
-            if let Some(c) = self.bg.term_constant() {
-                try_term!(t.bg(c));
+            if let Color::Rgb(..) = style.bg {
+                ansi::write_io(t, &Style::default().bg(style.bg))?;
+            } else if let Some(c) = style.bg.term_constant() {
+                try_term!(t.bg(c), Error::UnsupportedColor(style.bg));
             }
 
             // For all attributes: Apply, when set.
-            if let Some(true) = self.get_bold() {
-                try_term!(t.attr(term::Attr::Bold));
+            if let Some(true) = style.get_bold() {
+                try_term!(t.attr(term::Attr::Bold), Error::UnsupportedAttr(Attr::Bold));
+            }
+            if let Some(true) = style.get_dim() {
+                try_term!(t.attr(term::Attr::Dim), Error::UnsupportedAttr(Attr::Dim));
             }
-            if let Some(true) = self.get_dim() {
-                try_term!(t.attr(term::Attr::Dim));
+            if let Some(u) = style.get_underline() {
+                try_term!(t.attr(term::Attr::Underline(u)), Error::UnsupportedAttr(Attr::Underline));
             }
-            if let Some(u) = self.get_underline() {
-                try_term!(t.attr(term::Attr::Underline(u)));
+            if let Some(true) = style.get_blink() {
+                try_term!(t.attr(term::Attr::Blink), Error::UnsupportedAttr(Attr::Blink));
             }
-            if let Some(true) = self.get_blink() {
-                try_term!(t.attr(term::Attr::Blink));
+            if let Some(true) = style.get_reverse() {
+                try_term!(t.attr(term::Attr::Reverse), Error::UnsupportedAttr(Attr::Reverse))
+            }
+            if let Some(true) = style.get_secure() {
+                try_term!(t.attr(term::Attr::Secure), Error::UnsupportedAttr(Attr::Secure))
+            }
+            if let Some(true) = style.get_italic() {
+                try_term!(t.attr(term::Attr::Italic(true)), Error::UnsupportedAttr(Attr::Italic))
+            }
+
+            // `term` doesn't know about these attributes at all, so we write
+            // them ourselves if the terminal supports them.
+            let mut raw = Style::default();
+            if o.caps.supports(Attr::Strikethrough) {
+                raw.set_strikethrough(style.get_strikethrough());
             }
-            if let Some(true) = self.get_reverse() {
-                try_term!(t.attr(term::Attr::Reverse))
+            if o.caps.supports(Attr::Overline) {
+                raw.set_overline(style.get_overline());
             }
-            if let Some(true) = self.get_secure() {
-                try_term!(t.attr(term::Attr::Secure))
+            if o.caps.underline_styles() {
+                raw.underline_color = style.underline_color;
+                if style.get_underline() == Some(true) {
+                    raw.set_underline_style(style.get_underline_style());
+                    raw.set_underline(style.get_underline_style().map(|_| true));
+                }
             }
+            ansi::write_io(t, &raw)?;
 
             Ok(())
         })
     }
 
+    /// Maps both colors of this style with `Color::downgrade`.
+    fn downgrade(&self, colors: u32) -> Style {
+        Style {
+            fg: self.fg.downgrade(colors),
+            bg: self.bg.downgrade(colors),
+            underline_color: self.underline_color.downgrade(colors),
+            .. *self
+        }
+    }
+
     /// `o` overrides values of `self`.
     fn and(&self, o: Style) -> Style {
-        // Some shortcuts for bitfields.
-        let ax = self.bold_dim_underline_blink;
-        let ay = o.bold_dim_underline_blink;
-        let bx = self.reverse_secure;
-        let by = o.reverse_secure;
-
         // The following is equivalent to
         //     `s.set_attr(o.get_attr().and(self.get_attr()));`
         // for every attribute.
@@ -539,26 +868,200 @@ impl Style {
         // The resulting setbit is just an bitwise OR of both input setbits.
         // The resulting valuebit is either the one of y (if y's set bit is
         // set) or the one of x (otherwise).
-        let az = ((ax | ay) & 0b10101010) | (((ay >> 1) & ay | !(ay >> 1) & ax) & 0b01010101);
-        let bz = ((bx | by) & 0b10101010) | (((by >> 1) & by | !(by >> 1) & bx) & 0b01010101);
+        fn merge(x: u8, y: u8) -> u8 {
+            ((x | y) & 0b10101010) | (((y >> 1) & y | !(y >> 1) & x) & 0b01010101)
+        }
 
         Style {
             fg: if o.fg == Color::NotSet { self.fg } else { o.fg },
             bg: if o.bg == Color::NotSet { self.bg } else { o.bg },
-            bold_dim_underline_blink: az,
-            reverse_secure: bz,
+            underline_color: if o.underline_color == Color::NotSet {
+                self.underline_color
+            } else {
+                o.underline_color
+            },
+            underline_style: o.underline_style.or(self.underline_style),
+            bold_dim_underline_blink:
+                merge(self.bold_dim_underline_blink, o.bold_dim_underline_blink),
+            reverse_secure_italic_strikethrough: merge(
+                self.reverse_secure_italic_strikethrough,
+                o.reverse_secure_italic_strikethrough,
+            ),
+            overline: merge(self.overline, o.overline),
         }
     }
 
-    /// Resets the whole terminal and applies this style.
-    fn revert_to(&self) -> Result<(), fmt::Error> {
-        TERM.with(|term_opt| {
-            let mut tmut = term_opt.borrow_mut();
-            tmut.as_mut()
-                .and_then(|t| t.reset().ok())
-                .ok_or(fmt::Error)
+    /// Returns the escape sequence that changes the style `from` into `to`,
+    /// containing only what differs. See `Transition` for details.
+    pub fn transition(from: Style, to: Style) -> Transition {
+        Transition::new(from, to)
+    }
+
+    /// Changes the terminal from this style to `to`. Only the properties that
+    /// differ are applied, unless resetting the terminal is shorter or
+    /// properties have to be turned off on a terminal that doesn't understand
+    /// ANSI escape sequences.
+    fn switch_to(&self, to: &Style, target: Target) -> Result<(), Error> {
+        let on = output::with_output(target, |o| {
+            if !o.enabled() {
+                return Ok(None);
+            }
+            let t = match o.term.as_mut() {
+                None => return Err(Error::NoTerminal(target)),
+                Some(t) => t,
+            };
+            let colors = o.caps.colors();
+            let (from, to) = (self.downgrade(colors), to.downgrade(colors));
+
+            // `term` can't turn off single attributes, so we write the escape
+            // sequence for that ourselves. Terminals that don't understand
+            // escape sequences (the Windows console) are reset instead.
+            let mut off = String::new();
+            let on = ansi::write_off(&mut off, &from, &to)
+                .expect("writing into a `String` can't fail");
+            if Transition::new(from, to).resets() || !(off.is_empty() || o.ansi) {
+                t.reset().map_err(|e| Error::from_term(e, Error::UnsupportedAttr(Attr::Plain)))?;
+                Ok(Some(to))
+            } else {
+                t.write_all(off.as_bytes())?;
+                Ok(Some(on))
+            }
         })?;
-        self.apply()
+
+        match on {
+            Some(on) => on.apply(target),
+            None => Ok(()),
+        }
+    }
+}
+
+/// `const` versions of the `ToStyle` methods, so that styles can be built in
+/// `const` and `static` items, e.g. for a table of styles:
+///
+/// ```
+/// # use term_painter::{Attr, Color, Style, ToStyle, UnderlineStyle};
+/// static LEVELS: [(&str, Style); 3] = [
+///     ("error", Style::new().fg(Color::Red).bold()),
+///     ("warning", Style::new().fg(Color::Yellow).underline_style(UnderlineStyle::Curly)),
+///     ("note", Style::new().with_attr(Attr::Italic, Some(true))),
+/// ];
+/// println!("{}", LEVELS[0].1.paint("error"));
+/// ```
+///
+/// They behave exactly like the `ToStyle` methods of the same name (which
+/// they take precedence over when called on a `Style`).
+impl Style {
+    /// Creates the default style, which doesn't change anything. This is the
+    /// same as `Style::default()`.
+    pub const fn new() -> Style {
+        Style {
+            fg: Color::NotSet,
+            bg: Color::NotSet,
+            underline_color: Color::NotSet,
+            underline_style: None,
+            bold_dim_underline_blink: 0,
+            reverse_secure_italic_strikethrough: 0,
+            overline: 0,
+        }
+    }
+
+    /// Sets the foreground (text) color.
+    pub const fn fg(mut self, c: Color) -> Style {
+        self.fg = c;
+        self
+    }
+
+    /// Sets the background color.
+    pub const fn bg(mut self, c: Color) -> Style {
+        self.bg = c;
+        self
+    }
+
+    /// Sets the attribute to `v`: `Some(true)` enables it, `Some(false)`
+    /// disables it and `None` leaves it as it is in the surrounding style.
+    /// Setting `Plain` does nothing.
+    pub const fn with_attr(self, attr: Attr, v: Option<bool>) -> Style {
+        match attr {
+            Attr::Plain => self,
+            Attr::Bold => self.with_bold(v),
+            Attr::Dim => self.with_dim(v),
+            Attr::Underline => self.with_underline(v),
+            Attr::Blink => self.with_blink(v),
+            Attr::Reverse => self.with_reverse(v),
+            Attr::Secure => self.with_secure(v),
+            Attr::Italic => self.with_italic(v),
+            Attr::Strikethrough => self.with_strikethrough(v),
+            Attr::Overline => self.with_overline(v),
+        }
+    }
+
+    /// Makes the text bold.
+    pub const fn bold(self) -> Style {
+        self.with_bold(Some(true))
+    }
+
+    /// Dim mode.
+    pub const fn dim(self) -> Style {
+        self.with_dim(Some(true))
+    }
+
+    /// Underlines the text.
+    pub const fn underline(self) -> Style {
+        self.with_underline(Some(true))
+    }
+
+    /// Removes underline-attribute.
+    pub const fn not_underline(self) -> Style {
+        self.with_underline(Some(false))
+    }
+
+    /// Makes the text blink.
+    pub const fn blink(self) -> Style {
+        self.with_blink(Some(true))
+    }
+
+    /// Swaps foreground and background color.
+    pub const fn reverse(self) -> Style {
+        self.with_reverse(Some(true))
+    }
+
+    /// Secure mode, also called conceal or hidden mode: the text is not
+    /// visible.
+    pub const fn secure(self) -> Style {
+        self.with_secure(Some(true))
+    }
+
+    /// Italic text. Not supported by all terminals.
+    pub const fn italic(self) -> Style {
+        self.with_italic(Some(true))
+    }
+
+    /// Crosses out the text. Not supported by all terminals.
+    pub const fn strikethrough(self) -> Style {
+        self.with_strikethrough(Some(true))
+    }
+
+    /// Draws a line above the text. Not supported by all terminals.
+    pub const fn overline(self) -> Style {
+        self.with_overline(Some(true))
+    }
+
+    /// Underlines the text with the given style (e.g. a curly line).
+    pub const fn underline_style(self, u: UnderlineStyle) -> Style {
+        self.with_underline(Some(true)).with_underline_style(Some(u))
+    }
+
+    /// Sets the underline style without enabling the underline attribute
+    /// (see `set_underline_style`).
+    pub const fn with_underline_style(mut self, u: Option<UnderlineStyle>) -> Style {
+        self.underline_style = u;
+        self
+    }
+
+    /// Sets the color of the underline.
+    pub const fn underline_color(mut self, c: Color) -> Style {
+        self.underline_color = c;
+        self
     }
 }
 
@@ -576,13 +1079,197 @@ impl ToStyle for Style {
 pub struct Painted<T> {
     style: Style,
     obj: T,
+    ansi: bool,
+    target: Target,
+    link: Option<String>,
+}
+
+impl<T> Painted<T> {
+    /// Sets the output stream the style is applied to when this object is
+    /// printed. The default is `Target::Stdout`, so this is needed when
+    /// printing to stderr:
+    ///
+    /// ```
+    /// # use term_painter::{Color, Target, ToStyle};
+    /// eprintln!("{}", Color::Red.paint("error").target(Target::Stderr));
+    /// ```
+    ///
+    /// This has no effect if `ansi()` is used.
+    pub fn target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Makes this `Painted` write ANSI escape sequences into the formatter
+    /// instead of changing the state of the terminal. That way, the styling
+    /// ends up in the resulting string when used with `format!`, `write!` or
+    /// `to_string()`:
+    ///
+    /// ```
+    /// # use term_painter::{Color, ToStyle};
+    /// let s = format!("{}", Color::Red.paint("hi").ansi());
+    /// assert_eq!(s, "\x1b[31mhi\x1b[0m");
+    /// ```
+    ///
+    /// Nested `Painted` objects in this mode restore the style of the outer
+    /// one once they are done, writing only the properties that changed. As
+    /// it's unknown where the resulting string ends up, colors are not
+    /// downgraded to the terminal's color depth; use `Color::downgrade` for
+    /// that. Note that non-ANSI terminals (like `cmd` on older Windows
+    /// versions) will print the escape sequences as garbage.
+    pub fn ansi(mut self) -> Self {
+        self.ansi = true;
+        self
+    }
+
+    /// Turns the printed object into a hyperlink to `url` (via the OSC 8
+    /// escape sequence), which can be clicked in many terminals:
+    ///
+    /// ```
+    /// # use term_painter::{Attr, ToStyle};
+    /// println!("See {}", Attr::Underline.paint("the docs").link("https://docs.rs"));
+    /// ```
+    ///
+    /// If the terminal doesn't support hyperlinks or styling is disabled by
+    /// the `ColorChoice`, only the object is printed. Hyperlink support can't
+    /// be queried, so it's guessed from environment variables set by
+    /// terminals known to support them. `FORCE_HYPERLINK` can be set to `1`
+    /// or `0` to override that guess.
+    pub fn link<U: Into<String>>(mut self, url: U) -> Self {
+        self.link = Some(url.into());
+        self
+    }
+
+    /// Applies the style to the terminal while `inner` writes `obj`.
+    fn fmt_term<F>(&self, inner: F) -> Result<(), fmt::Error>
+        where F: FnOnce() -> Result<(), fmt::Error>
+    {
+        let link = self.term_link();
+
+        if let Some(url) = link {
+            self.write_link_start(url);
+        }
+
+        let res = self.style.with_target(self.target, inner);
+
+        if link.is_some() {
+            output::with_output(self.target, |o| o.write_raw(ansi::LINK_END));
+        }
+
+        res
+    }
+
+    /// Returns the URL of the link if there is one and the terminal supports
+    /// hyperlinks.
+    fn term_link(&self) -> Option<&String> {
+        let target = self.target;
+        self.link.as_ref()
+            .filter(|_| output::with_output(target, |o| o.enabled() && o.caps.hyperlinks()))
+    }
+
+    fn write_link_start(&self, url: &str) {
+        let mut start = String::new();
+        ansi::write_link_start(&mut start, url).expect("writing into a `String` can't fail");
+        output::with_output(self.target, |o| o.write_raw(&start));
+    }
+
+    /// Writes `obj` with ANSI escape sequences before and after it.
+    fn fmt_ansi<F>(&self, f: &mut fmt::Formatter, inner: F) -> Result<(), fmt::Error>
+        where F: FnOnce(&mut fmt::Formatter) -> Result<(), fmt::Error>
+    {
+        if !color_choice().enabled(None) {
+            return inner(f);
+        }
+
+        match self.link {
+            Some(ref url) if capabilities::detect_hyperlinks() => {
+                ansi::write_link_start(f, url)?;
+                let res = self.fmt_ansi_style(f, inner);
+                f.write_str(ansi::LINK_END)?;
+                res
+            }
+            _ => self.fmt_ansi_style(f, inner),
+        }
+    }
+
+    fn fmt_ansi_style<F>(&self, f: &mut fmt::Formatter, inner: F) -> Result<(), fmt::Error>
+        where F: FnOnce(&mut fmt::Formatter) -> Result<(), fmt::Error>
+    {
+        // Nothing to do, if nothing would change
+        if self.style == Style::default() {
+            return inner(f);
+        }
+
+        // The outermost `Painted` starts from the default style, as it's
+        // unknown where the string ends up
+        let outer = ANSI_STYLE.with(|curr| curr.get());
+        let before = outer.unwrap_or_default();
+        let after = before.and(self.style);
+        write!(f, "{}", Style::transition(before, after))?;
+        ANSI_STYLE.with(|curr| curr.set(Some(after)));
+
+        let res = inner(f);
+
+        ANSI_STYLE.with(|curr| curr.set(outer));
+        write!(f, "{}", Style::transition(after, before))?;
+
+        res
+    }
+}
+
+impl<T: fmt::Display> Painted<T> {
+    /// Writes the object to `w` with the style applied. This is like
+    /// `write!(w, "{}", painted)`, but returns an error if the style can't be
+    /// applied or the previous style can't be restored. `w` should write to
+    /// the stream given by `target` (stdout by default), as the style is
+    /// applied to that stream.
+    ///
+    /// ```
+    /// # use term_painter::{Color, ToStyle};
+    /// # use std::io;
+    /// if let Err(e) = Color::Red.paint("error").try_write_to(&mut io::stdout()) {
+    ///     eprintln!("styling failed: {}", e);
+    /// }
+    /// ```
+    ///
+    /// If `ansi()` is used, the escape sequences are written into `w`, so
+    /// only writing to `w` can fail.
+    pub fn try_write_to<W: io::Write + ?Sized>(&self, w: &mut W) -> Result<(), Error> {
+        if self.ansi {
+            return Ok(write!(w, "{}", self)?);
+        }
+
+        // The style is applied to the terminal directly, so everything that
+        // was written to `w` before has to get there first.
+        w.flush()?;
+        output::try_push_style(self.target, self.style)?;
+
+        let link = self.term_link();
+        if let Some(url) = link {
+            self.write_link_start(url);
+        }
+
+        let res = write!(w, "{}", self.obj).and_then(|_| w.flush());
+
+        if link.is_some() {
+            output::with_output(self.target, |o| o.write_raw(ansi::LINK_END));
+        }
+        let pop = output::try_pop_style(self.target);
+
+        res?;
+        pop
+    }
 }
 
 macro_rules! impl_format {
     ($symbol:expr, $fmt:ident) => {
         impl<T: fmt::$fmt> fmt::$fmt for Painted<T> {
             fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-                self.style.with(|| fmt::$fmt::fmt(&self.obj, f))
+                if self.ansi {
+                    self.fmt_ansi(f, |f| fmt::$fmt::fmt(&self.obj, f))
+                } else {
+                    self.fmt_term(|| fmt::$fmt::fmt(&self.obj, f))
+                }
             }
         }
     }
@@ -604,7 +1291,15 @@ impl_format!("{:E}", UpperExp);
 mod test {
     use super::Color::*;
     use super::Attr::*;
-    use super::{ToStyle, Style};
+    use std::collections::HashMap;
+    use std::env;
+    use std::io;
+    use std::sync::{Arc, Mutex};
+    use term::terminfo::TermInfo;
+    use term::TerminfoTerminal;
+    use super::{ToStyle, Style, StyledText, ColorChoice, UnderlineStyle, Error, Target};
+    use super::{output, Capabilities};
+    use backend::AnsiTerminal;
 
     #[test]
     fn modifier_order() {
@@ -618,6 +1313,22 @@ mod test {
         assert_eq!(Bold.to_style(), Plain.bold());
     }
 
+    #[test]
+    fn const_builders() {
+        const STYLE: Style = Style::new().fg(Red).bg(Blue).bold().italic().not_underline()
+            .with_attr(Dim, Some(false)).underline_color(Green);
+        assert_eq!(Style::new(), Style::default());
+        assert_eq!(
+            STYLE,
+            ToStyle::italic(Red.bg(Blue).bold().not_underline().underline_color(Green))
+                .to_mapped_style(|s| s.set_dim(Some(false))),
+        );
+        assert_eq!(
+            Style::new().underline_style(UnderlineStyle::Dashed),
+            ToStyle::underline_style(Plain, UnderlineStyle::Dashed),
+        );
+    }
+
     #[test]
     fn modifier_override() {
         // The latter modifier should override the one before
@@ -627,6 +1338,178 @@ mod test {
         assert_eq!(Red.fg(Blue), Blue.to_style());
     }
 
+    #[test]
+    fn painted_ansi() {
+        ColorChoice::Always.with(|| {
+            assert_eq!(Plain.paint("a").ansi().to_string(), "a");
+            assert_eq!(format!("{:>3}", Red.paint(7).ansi()), "\x1b[31m  7\x1b[0m");
+
+            // The inner `Painted` restores the outer style
+            let inner = Bold.paint("b").ansi();
+            assert_eq!(
+                format!("{}", Red.paint(format_args!("x{}y", inner)).ansi()),
+                "\x1b[31mx\x1b[1mb\x1b[22my\x1b[0m",
+            );
+
+            // The style of the terminal doesn't end up in the string
+            output::with_output(Target::Stdout, |o| o.term = None);
+            assert_eq!(Red.with(|| Red.paint("x").ansi().to_string()), "\x1b[31mx\x1b[0m");
+            assert_eq!(Blue.with(|| Red.paint("x").ansi().to_string()), "\x1b[31mx\x1b[0m");
+            assert_eq!(
+                Blue.with(|| StyledText::from(Red.paint("x")).ansi().to_string()),
+                "\x1b[31mx\x1b[0m",
+            );
+        });
+
+        ColorChoice::Never.with(|| {
+            assert_eq!(Red.bold().paint("a").ansi().to_string(), "a");
+        });
+    }
+
+    /// Captures what is written to a terminal.
+    #[derive(Clone, Default)]
+    struct Buf(Arc<Mutex<Vec<u8>>>);
+
+    impl Buf {
+        fn take(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().split_off(0)).unwrap()
+        }
+    }
+
+    impl io::Write for Buf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn terminfo_transitions() {
+        let mut info = TermInfo {
+            names: vec!["test".to_string()],
+            bools: HashMap::new(),
+            numbers: HashMap::new(),
+            strings: HashMap::new(),
+        };
+        info.numbers.insert("colors", 8);
+        info.strings.insert("sgr0", b"\x1b(B\x1b[m".to_vec());
+        info.strings.insert("setaf", b"\x1b[3%p1%dm".to_vec());
+        info.strings.insert("setab", b"\x1b[4%p1%dm".to_vec());
+        info.strings.insert("bold", b"\x1b[1m".to_vec());
+
+        // A terminfo terminal writing into a buffer
+        let buf = Buf::default();
+        let t = TerminfoTerminal::new_with_terminfo(buf.clone(), info);
+        output::with_output(Target::Stderr, |o| {
+            o.term = Some(Box::new(Box::new(t)));
+            o.caps = Capabilities::with_colors(8);
+            // Like `Output::open` does for terminals found via terminfo
+            o.ansi = true;
+        });
+
+        ColorChoice::Always.with(|| {
+            Bold.with_target(Target::Stderr, || Red.with_target(Target::Stderr, || {}));
+        });
+        // Only red is turned off, the reset at the end is shorter than `22`
+        assert_eq!(buf.take(), "\x1b[1m\x1b[31m\x1b[39m\x1b(B\x1b[m");
+    }
+
+    #[test]
+    fn links() {
+        // The only test that touches this variable, so it doesn't race
+        env::set_var("FORCE_HYPERLINK", "1");
+        let link = || Bold.paint("docs").link("https://example.com/\x1b\x07x");
+
+        ColorChoice::Always.with(|| {
+            assert_eq!(
+                link().ansi().to_string(),
+                "\x1b]8;;https://example.com/x\x1b\\\x1b[1mdocs\x1b[0m\x1b]8;;\x1b\\",
+            );
+
+            let buf = Buf::default();
+            output::with_output(Target::Stderr, |o| {
+                o.term = Some(Box::new(AnsiTerminal(buf.clone())));
+                o.caps = Capabilities::with_colors(8);
+            });
+            assert_eq!(link().target(Target::Stderr).to_string(), "docs");
+            assert_eq!(
+                buf.take(),
+                "\x1b]8;;https://example.com/x\x1b\\\x1b[1m\x1b[0m\x1b]8;;\x1b\\",
+            );
+
+            // Without hyperlink support, only the style is applied
+            output::with_output(Target::Stderr, |o| {
+                o.caps = Capabilities::with_colors(8).no_hyperlinks();
+            });
+            assert_eq!(link().target(Target::Stderr).to_string(), "docs");
+            assert_eq!(buf.take(), "\x1b[1m\x1b[0m");
+
+            env::set_var("FORCE_HYPERLINK", "0");
+            assert_eq!(link().ansi().to_string(), "\x1b[1mdocs\x1b[0m");
+            env::set_var("FORCE_HYPERLINK", "1");
+        });
+
+        ColorChoice::Never.with(|| {
+            assert_eq!(link().ansi().to_string(), "docs");
+            assert_eq!(link().target(Target::Stderr).to_string(), "docs");
+        });
+        env::remove_var("FORCE_HYPERLINK");
+    }
+
+    #[test]
+    fn fallible() {
+        // Pretend that stderr is not a terminal
+        output::with_output(Target::Stderr, |o| o.term = None);
+
+        ColorChoice::Always.with(|| {
+            match Red.try_with_target(Target::Stderr, || panic!("must not be called")) {
+                Err(Error::NoTerminal(Target::Stderr)) => {}
+                other => panic!("unexpected result: {:?}", other),
+            }
+            assert!(output::with_output(Target::Stderr, |o| o.stack.is_empty()));
+
+            let mut buf = Vec::new();
+            match Red.paint("x").target(Target::Stderr).try_write_to(&mut buf) {
+                Err(Error::NoTerminal(Target::Stderr)) => {}
+                other => panic!("unexpected result: {:?}", other),
+            }
+            assert!(buf.is_empty());
+
+            Red.paint("x").ansi().try_write_to(&mut buf).unwrap();
+            assert_eq!(buf, b"\x1b[31mx\x1b[0m");
+        });
+
+        ColorChoice::Never.with(|| {
+            assert_eq!(Red.try_with_target(Target::Stderr, || 3).unwrap(), 3);
+        });
+    }
+
+    #[test]
+    fn style_and_rgb() {
+        let s1 = Rgb(1, 2, 3).bg(Red);
+        let s2 = Plain.bg(Rgb(4, 5, 6));
+
+        assert_eq!(s1.and(s2), Rgb(1, 2, 3).bg(Rgb(4, 5, 6)));
+        assert_eq!(s2.and(s1), s1);
+    }
+
+    #[test]
+    fn color_downgrade() {
+        assert_eq!(Rgb(1, 2, 3).downgrade(1 << 24), Rgb(1, 2, 3));
+        assert_eq!(Rgb(0x1e, 0x1e, 0x1e).downgrade(256), Custom(234));
+        assert_eq!(Custom(196).downgrade(256), Custom(196));
+        assert_eq!(Custom(196).downgrade(16), BrightRed);
+        assert_eq!(Custom(196).downgrade(8), Red);
+        assert_eq!(Custom(12).downgrade(8), Custom(4));
+        assert_eq!(Custom(300).downgrade(256), NotSet);
+        assert_eq!(BrightCyan.downgrade(16), BrightCyan);
+        assert_eq!(BrightCyan.downgrade(8), Cyan);
+        assert_eq!(Red.downgrade(0), NotSet);
+        assert_eq!(NotSet.downgrade(8), NotSet);
+    }
+
     #[test]
     fn style_and() {
         let s1 = Style::default().bold().not_underline();
@@ -639,5 +1522,22 @@ mod test {
         assert_eq!(s2.and(s1), r2);
         assert_eq!(s2.and(s1).and(s3), r2);
         assert_eq!(s2.and(s3), r1);
+
+        let s4 = Style::default().italic().overline();
+        let r3 = Style::default().bold().underline().italic().overline();
+        assert_eq!(s2.and(s3).and(s4), r3);
+        assert_eq!(Italic.strikethrough().and(s4), Strikethrough.to_style().and(s4));
+    }
+
+    #[test]
+    fn style_and_underline() {
+        let curly = Plain.underline_style(UnderlineStyle::Curly).underline_color(Red);
+        let dotted = Plain.underline_style(UnderlineStyle::Dotted);
+
+        assert_eq!(curly.and(dotted), dotted.underline_color(Red));
+        assert_eq!(dotted.and(curly), curly);
+        assert_eq!(curly.and(Plain.not_underline()).get_underline(), Some(false));
+        assert_eq!(curly.and(Plain.underline_color(Blue)).get_underline_style(),
+            Some(UnderlineStyle::Curly));
     }
 }
@@ -0,0 +1,346 @@
+//! A small markup language for text with multiple styles.
+
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+
+use {ParseError, Style, StyledText};
+
+/// Parses text with style tags into a `StyledText`:
+///
+/// ```
+/// # use term_painter::{parse_markup, Attr, Color, ToStyle};
+/// let text = parse_markup("[bold red]error[/]: [[x] is [underline]not [italic]set").unwrap();
+/// assert_eq!(text.plain_text(), "error: [x] is not set");
+/// assert_eq!(text.spans()[3].0, Attr::Underline.italic());
+/// ```
+///
+/// A tag like `[bold red]` contains a style (see `Style`'s `FromStr` impl)
+/// that is applied up to the matching `[/]`. Tags can be nested; the style of
+/// an inner tag is applied on top of the outer ones, overriding only the
+/// properties it sets (like nested `ToStyle::with` calls). Tags that are
+/// still open at the end are closed implicitly. A literal `[` is written as
+/// `[[`, while `]` outside of a tag needs no escaping.
+///
+/// To build markup with `format!`-like arguments, use `paint_markup!`.
+pub fn parse_markup(s: &str) -> Result<StyledText, MarkupError> {
+    parse(s, &[])
+}
+
+/// Parses `s`. The byte ranges in `literal` (sorted and not overlapping) are
+/// taken as text without looking for markup in them.
+fn parse(s: &str, literal: &[Range<usize>]) -> Result<StyledText, MarkupError> {
+    let mut out = StyledText::new();
+    // The styles of the open tags, each merged with the ones before
+    let mut stack: Vec<Style> = Vec::new();
+    let mut text = String::new();
+
+    let mut literal = literal.iter().filter(|r| !r.is_empty()).peekable();
+    let mut chars = s.char_indices().peekable();
+    while let Some((pos, c)) = chars.next() {
+        if let Some(r) = literal.next_if(|r| r.start == pos) {
+            text.push_str(&s[r.clone()]);
+            while chars.next_if(|&(i, _)| i < r.end).is_some() {}
+            continue;
+        }
+
+        // Markup (like a tag) can't extend into the next literal range
+        let markup = &s[..literal.peek().map_or(s.len(), |r| r.start)];
+        match c {
+            '[' if markup[pos + 1..].starts_with('[') => {
+                chars.next();
+                text.push('[');
+            }
+            '[' => {
+                let len = markup[pos..].find(']').ok_or(MarkupError::UnclosedBracket { pos })?;
+                let tag = &s[pos + 1..pos + len];
+                let curr = stack.last().cloned().unwrap_or_default();
+                out.push(curr, text.split_off(0));
+
+                if tag.trim() == "/" {
+                    stack.pop().ok_or(MarkupError::UnmatchedClose { pos })?;
+                } else {
+                    let style: Style = tag.parse().map_err(|e| MarkupError::Style {
+                        pos,
+                        tag: tag.to_string(),
+                        error: e,
+                    })?;
+                    stack.push(curr.and(style));
+                }
+
+                while chars.next_if(|&(i, _)| i <= pos + len).is_some() {}
+            }
+            c => text.push(c),
+        }
+    }
+
+    out.push(stack.last().cloned().unwrap_or_default(), text);
+    Ok(out)
+}
+
+/// Used by `paint_markup!`: collects the formatted markup and the byte
+/// ranges of the arguments in it, so that their text is never parsed as
+/// markup.
+#[doc(hidden)]
+pub struct MarkupArgs {
+    out: RefCell<String>,
+    args: RefCell<Vec<Range<usize>>>,
+}
+
+/// Used by `paint_markup!`: an argument that records where its text ends up
+/// when it's formatted.
+#[doc(hidden)]
+pub struct MarkupArg<'a, T: 'a> {
+    args: &'a MarkupArgs,
+    value: T,
+}
+
+/// Appends everything to the output of `MarkupArgs`. The `RefCell` is only
+/// borrowed while writing, so that arguments can query the length of the
+/// output in between.
+struct Writer<'a>(&'a RefCell<String>);
+
+impl<'a> fmt::Write for Writer<'a> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.borrow_mut().push_str(s);
+        Ok(())
+    }
+}
+
+impl MarkupArgs {
+    pub fn new() -> Self {
+        MarkupArgs {
+            out: RefCell::new(String::new()),
+            args: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Wraps an argument of the format string.
+    pub fn arg<T>(&self, value: T) -> MarkupArg<'_, T> {
+        MarkupArg { args: self, value }
+    }
+
+    /// Formats the markup (with arguments created by `arg`), parses it and
+    /// panics if it's invalid.
+    pub fn parse(&self, markup: fmt::Arguments) -> StyledText {
+        fmt::write(&mut Writer(&self.out), markup)
+            .expect("a formatting trait implementation returned an error");
+        match parse(&self.out.borrow(), &self.args.borrow()) {
+            Ok(text) => text,
+            Err(e) => panic!("invalid markup in `paint_markup!`: {}", e),
+        }
+    }
+}
+
+impl Default for MarkupArgs {
+    fn default() -> Self {
+        MarkupArgs::new()
+    }
+}
+
+macro_rules! impl_format {
+    ($($fmt:ident)*) => {
+        $(
+            impl<'a, T: fmt::$fmt> fmt::$fmt for MarkupArg<'a, T> {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    let start = self.args.out.borrow().len();
+                    fmt::$fmt::fmt(&self.value, f)?;
+                    let end = self.args.out.borrow().len();
+                    self.args.args.borrow_mut().push(start..end);
+                    Ok(())
+                }
+            }
+        )*
+    }
+}
+
+impl_format!(Display Debug Octal LowerHex UpperHex Binary LowerExp UpperExp);
+
+/// Used by `paint_markup!`: fails the compilation if the format string
+/// captures variables by name (like `{path}`), as those can't be kept apart
+/// from the markup.
+#[doc(hidden)]
+pub const fn check_markup_format(s: &str) {
+    let s = s.as_bytes();
+    let mut i = 0;
+    while i + 1 < s.len() {
+        if s[i] == b'{' {
+            let c = s[i + 1];
+            if c == b'_' || c.is_ascii_alphabetic() || c >= 0x80 {
+                panic!("`paint_markup!` doesn't support named arguments; pass them explicitly");
+            }
+            if c == b'{' {
+                i += 1;
+            }
+        }
+        i += 1;
+    }
+}
+
+/// Creates a `StyledText` from markup (see `parse_markup`) with arguments
+/// like `format!`:
+///
+/// ```
+/// #[macro_use]
+/// extern crate term_painter;
+///
+/// # fn main() {
+/// let path = "src/[main].rs";
+/// let text = paint_markup!("[bold red]error[/]: file [underline]{}[/] not found", path);
+/// println!("{}", text);
+/// assert_eq!(text.plain_text(), "error: file src/[main].rs not found");
+/// # }
+/// ```
+///
+/// The arguments are never interpreted as markup, so they don't need to be
+/// escaped. For the same reason, arguments can't be used inside of tags and
+/// variables can't be captured by name in the format string:
+///
+/// ```compile_fail
+/// #[macro_use]
+/// extern crate term_painter;
+///
+/// # fn main() {
+/// let path = "src/[main].rs";
+/// paint_markup!("file [underline]{path}[/] not found");
+/// # }
+/// ```
+///
+/// # Panics
+///
+/// Panics if the markup is invalid, e.g. because of an unknown style in a
+/// tag or a `[` that is not escaped.
+#[macro_export]
+macro_rules! paint_markup {
+    ($fmt:expr $(, $arg:expr)* $(,)*) => {{
+        const _: () = $crate::check_markup_format($fmt);
+        let args = $crate::MarkupArgs::new();
+        args.parse(format_args!($fmt, $(args.arg(&$arg)),*))
+    }};
+}
+
+/// Error returned when parsing markup fails. `pos` is the byte offset of
+/// the offending tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MarkupError {
+    /// A `[` has no matching `]`.
+    UnclosedBracket { pos: usize },
+    /// A `[/]` without an open tag.
+    UnmatchedClose { pos: usize },
+    /// The style in the tag is invalid.
+    Style { pos: usize, tag: String, error: ParseError },
+}
+
+impl fmt::Display for MarkupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MarkupError::UnclosedBracket { pos } => write!(
+                f,
+                "byte {}: `[` without matching `]` (use `[[` for a literal `[`)",
+                pos,
+            ),
+            MarkupError::UnmatchedClose { pos } => {
+                write!(f, "byte {}: `[/]` without an open tag", pos)
+            }
+            MarkupError::Style { pos, ref tag, ref error } => {
+                write!(f, "byte {}: invalid style in tag `[{}]`: {}", pos, tag, error)
+            }
+        }
+    }
+}
+
+impl Error for MarkupError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            MarkupError::Style { ref error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use Color::*;
+    use Attr::*;
+    use {ParseError, Style, ToStyle};
+    use super::{parse_markup, MarkupError};
+
+    fn spans(s: &str) -> Vec<(Style, String)> {
+        parse_markup(s).unwrap().spans().to_vec()
+    }
+
+    #[test]
+    fn tags() {
+        assert_eq!(spans(""), vec![]);
+        assert_eq!(spans("plain"), vec![(Plain.to_style(), "plain".to_string())]);
+        assert_eq!(
+            spans("[red]a[underline]b[not-underline on blue]c[/]d[/]e[ ]f"),
+            vec![
+                (Red.to_style(), "a".to_string()),
+                (Red.underline(), "b".to_string()),
+                (Red.not_underline().bg(Blue), "c".to_string()),
+                (Red.underline(), "d".to_string()),
+                (Red.to_style(), "ef".to_string()),
+            ],
+        );
+        assert_eq!(spans("[[red]] ]"), vec![(Plain.to_style(), "[red]] ]".to_string())]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse_markup("a[red"), Err(MarkupError::UnclosedBracket { pos: 1 }));
+        assert_eq!(parse_markup("[red]a[/][/]"), Err(MarkupError::UnmatchedClose { pos: 9 }));
+        assert_eq!(
+            parse_markup("[redd]").unwrap_err(),
+            MarkupError::Style {
+                pos: 0,
+                tag: "redd".to_string(),
+                error: ParseError::UnknownWord("redd".to_string()),
+            },
+        );
+    }
+
+    #[test]
+    fn macro_args() {
+        let text = paint_markup!("[bold]{}[/] {:>4} {:?}", "[red]", 7, "[/]");
+        assert_eq!(text.spans(), &[
+            (Bold.to_style(), "[red]".to_string()),
+            (Plain.to_style(), "    7 \"[/]\"".to_string()),
+        ][..]);
+
+        assert_eq!(paint_markup!("[[{{}}").plain_text(), "[{}");
+        assert_eq!(paint_markup!("{}{}", 1, 2,).plain_text(), "12");
+    }
+
+    #[test]
+    fn macro_args_are_literal() {
+        let text = paint_markup!("[bold]{}[/] x", "a\u{fdd1}[oops");
+        assert_eq!(text.spans(), &[
+            (Bold.to_style(), "a\u{fdd1}[oops".to_string()),
+            (Plain.to_style(), " x".to_string()),
+        ][..]);
+
+        assert_eq!(paint_markup!("\u{fdd0}{}{0}[[", "[").plain_text(), "\u{fdd0}[[[");
+        assert_eq!(paint_markup!("{:>3}{{x}}", "").plain_text(), "   {x}");
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid markup")]
+    fn macro_panics() {
+        paint_markup!("[bold {}", 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid markup")]
+    fn macro_arg_in_tag() {
+        paint_markup!("[{}]", "bold");
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid markup")]
+    fn macro_escape_before_arg() {
+        paint_markup!("[{}", "[x");
+    }
+}
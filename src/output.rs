@@ -0,0 +1,290 @@
+//! Terminal handles for the different output streams and the style that is
+//! currently active on each of them.
+
+use std::cell::RefCell;
+use std::io::{self, IsTerminal};
+
+use term;
+use term::terminfo::TermInfo;
+
+use backend::{self, AnsiTerminal, Backend};
+use capabilities::{self, Capabilities};
+use {color_choice, Error, Style, ToStyle};
+
+/// The output stream a style is applied to.
+///
+/// By default, everything is styled on stdout. If you print to stderr (e.g.
+/// via `eprintln!`), use `ToStyle::with_target` or `Painted::target` to
+/// style stderr instead:
+///
+/// ```
+/// # use term_painter::{Color, Target, ToStyle};
+/// eprintln!("{}", Color::Red.paint("error").target(Target::Stderr));
+///
+/// Color::Yellow.with_target(Target::Stderr, || {
+///     eprintln!("warning");
+/// });
+/// ```
+///
+/// To style text written to any other `io::Write` sink, use `Painted::ansi`
+/// and write the `Painted` object to the sink.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Target {
+    Stdout,
+    Stderr,
+}
+
+/// The part of `term::Terminal` this crate uses. In contrast to
+/// `term::Terminal`, it has no associated `Output` type, so that the
+/// terminals of stdout and stderr can be stored as the same type.
+pub trait Terminal: io::Write {
+    fn fg(&mut self, color: term::color::Color) -> term::Result<()>;
+    fn bg(&mut self, color: term::color::Color) -> term::Result<()>;
+    fn attr(&mut self, attr: term::Attr) -> term::Result<()>;
+    fn reset(&mut self) -> term::Result<()>;
+    fn supports_attr(&self, attr: term::Attr) -> bool;
+}
+
+impl<T: term::Terminal + ?Sized> Terminal for Box<T> {
+    fn fg(&mut self, color: term::color::Color) -> term::Result<()> {
+        (**self).fg(color)
+    }
+    fn bg(&mut self, color: term::color::Color) -> term::Result<()> {
+        (**self).bg(color)
+    }
+    fn attr(&mut self, attr: term::Attr) -> term::Result<()> {
+        (**self).attr(attr)
+    }
+    fn reset(&mut self) -> term::Result<()> {
+        (**self).reset()
+    }
+    fn supports_attr(&self, attr: term::Attr) -> bool {
+        (**self).supports_attr(attr)
+    }
+}
+
+/// State of one output stream.
+pub struct Output {
+    /// The terminal handle or `None` if the stream is not a terminal.
+    pub term: Option<Box<dyn Terminal>>,
+    /// The styles pushed by `push_style`, each merged with the ones below.
+    /// The last one is currently active.
+    pub stack: Vec<Style>,
+    /// What the terminal supports.
+    pub caps: Capabilities,
+    /// Whether the terminal understands ANSI escape sequences, so that we can
+    /// write sequences `term` doesn't know about. Only the Windows console
+    /// doesn't.
+    pub ansi: bool,
+    /// The backend `term` was opened with.
+    backend: Backend,
+}
+
+impl Output {
+    fn new(target: Target) -> Self {
+        let mut output = Output {
+            term: None,
+            stack: Vec::new(),
+            caps: capabilities::detect(None, false, false),
+            ansi: false,
+            backend: backend::backend(),
+        };
+        output.open(target);
+        output
+    }
+
+    /// (Re)opens the terminal handle with the current backend.
+    fn open(&mut self, target: Target) {
+        fn terminfo<T: term::Terminal + ?Sized + 'static>(t: Option<Box<T>>) -> Option<Box<dyn Terminal>> {
+            t.map(|t| Box::new(t) as Box<dyn Terminal>)
+        }
+        let (terminfo, ansi, is_tty) = match target {
+            Target::Stdout => (
+                terminfo(term::stdout()),
+                Box::new(AnsiTerminal(io::stdout())) as Box<dyn Terminal>,
+                io::stdout().is_terminal(),
+            ),
+            Target::Stderr => (
+                terminfo(term::stderr()),
+                Box::new(AnsiTerminal(io::stderr())) as Box<dyn Terminal>,
+                io::stderr().is_terminal(),
+            ),
+        };
+
+        self.backend = backend::backend();
+        let (term, is_ansi) = match self.backend {
+            Backend::Terminfo => (terminfo, false),
+            Backend::Ansi => (Some(ansi), true),
+            Backend::Auto => match terminfo {
+                Some(t) => (Some(t), false),
+                None => (Some(ansi), true),
+            },
+        };
+        self.caps = capabilities::detect(term.as_deref(), is_tty, is_ansi);
+        // `term` only uses the Windows console if there is no terminfo
+        self.ansi = is_ansi || TermInfo::from_env().is_ok();
+        self.term = term;
+    }
+
+    /// Returns the style that is currently active.
+    pub fn curr(&self) -> Style {
+        self.stack.last().cloned().unwrap_or_default()
+    }
+
+    /// Writes `s` to the terminal as is. Errors are ignored.
+    pub fn write_raw(&mut self, s: &str) {
+        if let Some(t) = self.term.as_mut() {
+            let _ = t.write_all(s.as_bytes());
+        }
+    }
+
+    /// Returns whether styles should be applied to this stream according to
+    /// the current `ColorChoice`.
+    pub fn enabled(&self) -> bool {
+        color_choice().enabled(Some(self.caps.is_tty()))
+    }
+}
+
+thread_local!(
+    static STDOUT: RefCell<Output> = RefCell::new(Output::new(Target::Stdout))
+);
+thread_local!(
+    static STDERR: RefCell<Output> = RefCell::new(Output::new(Target::Stderr))
+);
+
+/// Calls `f` with the state of the given target. The state is borrowed while
+/// `f` runs, so `f` must not call `with_output` itself.
+pub fn with_output<F, R>(target: Target, f: F) -> R
+    where F: FnOnce(&mut Output) -> R
+{
+    let with = |o: &RefCell<Output>| {
+        let mut o = o.borrow_mut();
+        if o.backend != backend::backend() {
+            o.open(target);
+        }
+        f(&mut o)
+    };
+
+    match target {
+        Target::Stdout => STDOUT.with(with),
+        Target::Stderr => STDERR.with(with),
+    }
+}
+
+/// Applies `style` on top of the current style of `target` until the
+/// matching `pop_style` call. Only the properties that change are sent to
+/// the terminal.
+///
+/// This is what `ToStyle::with_target` does around its function. Use these
+/// functions directly if the styled section doesn't fit into a closure:
+///
+/// ```
+/// # use term_painter::{pop_style, push_style, Attr, Color, Target};
+/// push_style(Target::Stdout, Color::Red);
+/// print!("red ");
+/// push_style(Target::Stdout, Attr::Bold);
+/// print!("red and bold ");
+/// pop_style(Target::Stdout);
+/// println!("red again");
+/// pop_style(Target::Stdout);
+/// ```
+pub fn push_style<S: ToStyle>(target: Target, style: S) {
+    // The style stays on the stack even if applying it fails, so that the
+    // matching `pop_style` call doesn't remove the wrong style.
+    let (before, after) = push(target, style.to_style());
+    let _ = before.switch_to(&after, target);
+}
+
+/// Like `push_style`, but returns an error if applying the style fails. In
+/// that case, the style is not pushed, so `pop_style` must not be called.
+pub fn try_push_style<S: ToStyle>(target: Target, style: S) -> Result<(), Error> {
+    let (before, after) = push(target, style.to_style());
+    before.switch_to(&after, target).map_err(|e| {
+        with_output(target, |o| o.stack.pop());
+        e
+    })
+}
+
+/// Pushes `style` merged with the current style and returns the style before
+/// and after.
+fn push(target: Target, style: Style) -> (Style, Style) {
+    with_output(target, |o| {
+        let before = o.curr();
+        let after = before.and(style);
+        o.stack.push(after);
+        (before, after)
+    })
+}
+
+/// Replaces the style pushed last by `push_style` with `style` (merged with
+/// the styles below it), switching directly from one to the other.
+pub fn replace_style(target: Target, style: Style) {
+    let switch = with_output(target, |o| {
+        let below = o.stack.len().checked_sub(2).map(|i| o.stack[i]).unwrap_or_default();
+        o.stack.last_mut().map(|top| {
+            let before = *top;
+            *top = below.and(style);
+            (before, *top)
+        })
+    });
+
+    if let Some((before, after)) = switch {
+        let _ = before.switch_to(&after, target);
+    }
+}
+
+/// Removes the style pushed last by `push_style` and restores the style that
+/// was active before exactly. Does nothing if no style was pushed.
+pub fn pop_style(target: Target) {
+    let _ = try_pop_style(target);
+}
+
+/// Like `pop_style`, but returns an error if restoring the previous style
+/// fails. The style is removed from the stack in any case.
+pub fn try_pop_style(target: Target) -> Result<(), Error> {
+    let switch = with_output(target, |o| {
+        o.stack.pop().map(|popped| (popped, o.curr()))
+    });
+
+    match switch {
+        Some((popped, curr)) => popped.switch_to(&curr, target),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use Color::*;
+    use Attr::*;
+    use {ColorChoice, ToStyle};
+    use super::{pop_style, push_style, with_output, Target};
+
+    #[test]
+    fn style_stack() {
+        let curr = || with_output(Target::Stderr, |o| o.curr());
+
+        ColorChoice::Never.with(|| {
+            push_style(Target::Stderr, Red.bold());
+            push_style(Target::Stderr, Blue);
+            assert_eq!(curr(), Blue.bold());
+
+            // Pushing onto one stream doesn't affect the other one
+            assert_eq!(with_output(Target::Stdout, |o| o.curr()), Plain.to_style());
+
+            // An ANSI `Painted` inside `with` doesn't depend on the stack
+            Underline.with_target(Target::Stderr, || {
+                let s = Italic.paint("x").ansi().target(Target::Stderr);
+                ColorChoice::Always.with(|| {
+                    assert_eq!(s.to_string(), "\x1b[3mx\x1b[0m");
+                });
+                assert_eq!(curr(), Blue.bold().underline());
+            });
+
+            pop_style(Target::Stderr);
+            assert_eq!(curr(), Red.bold());
+            pop_style(Target::Stderr);
+            pop_style(Target::Stderr);
+            assert_eq!(curr(), Plain.to_style());
+        });
+    }
+}
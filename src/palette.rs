@@ -0,0 +1,92 @@
+//! RGB values of the standard terminal palette and helpers to find the
+//! closest palette entry for a given color. Used to downgrade colors for
+//! terminals that support fewer colors.
+
+/// RGB values of the 16 standard colors, as used by xterm.
+const STANDARD: [(u8, u8, u8); 16] = [
+    (0x00, 0x00, 0x00), (0xcd, 0x00, 0x00), (0x00, 0xcd, 0x00), (0xcd, 0xcd, 0x00),
+    (0x00, 0x00, 0xee), (0xcd, 0x00, 0xcd), (0x00, 0xcd, 0xcd), (0xe5, 0xe5, 0xe5),
+    (0x7f, 0x7f, 0x7f), (0xff, 0x00, 0x00), (0x00, 0xff, 0x00), (0xff, 0xff, 0x00),
+    (0x5c, 0x5c, 0xff), (0xff, 0x00, 0xff), (0x00, 0xff, 0xff), (0xff, 0xff, 0xff),
+];
+
+/// The channel values used by the 6x6x6 color cube (indices 16 to 231).
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Returns the RGB value of the given entry of the 256 color palette.
+pub fn rgb_of_index(idx: u8) -> (u8, u8, u8) {
+    match idx {
+        0..=15 => STANDARD[idx as usize],
+        16..=231 => {
+            let i = idx - 16;
+            (
+                CUBE_LEVELS[(i / 36) as usize],
+                CUBE_LEVELS[(i / 6 % 6) as usize],
+                CUBE_LEVELS[(i % 6) as usize],
+            )
+        }
+        _ => {
+            let v = 8 + 10 * (idx - 232);
+            (v, v, v)
+        }
+    }
+}
+
+/// Squared euclidean distance between two colors.
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+/// Returns the index of the color cube level closest to `v`.
+fn nearest_level(v: u8) -> u8 {
+    (0..6).min_by_key(|&i| (CUBE_LEVELS[i] as i32 - v as i32).abs()).unwrap() as u8
+}
+
+/// Returns the index of the closest color in the 256 color palette, not
+/// considering the 16 standard colors (their actual values vary a lot between
+/// terminals).
+pub fn nearest_256(rgb: (u8, u8, u8)) -> u8 {
+    let (r, g, b) = rgb;
+    let cube = 16 + 36 * nearest_level(r) + 6 * nearest_level(g) + nearest_level(b);
+
+    // The grayscale ramp has finer steps than the cube, so it might be closer
+    let avg = (r as u32 + g as u32 + b as u32) / 3;
+    let gray = 232 + ((avg.saturating_sub(3) / 10).min(23) as u8);
+
+    if distance(rgb, rgb_of_index(gray)) < distance(rgb, rgb_of_index(cube)) {
+        gray
+    } else {
+        cube
+    }
+}
+
+/// Returns the index of the closest of the 16 standard colors.
+pub fn nearest_16(rgb: (u8, u8, u8)) -> u8 {
+    (0..16u8).min_by_key(|&i| distance(rgb, STANDARD[i as usize])).unwrap()
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn palette_roundtrip() {
+        for idx in 16..=255 {
+            assert_eq!(nearest_256(rgb_of_index(idx)), idx);
+        }
+        for idx in 0..16 {
+            assert_eq!(nearest_16(STANDARD[idx as usize]), idx);
+        }
+    }
+
+    #[test]
+    fn nearest() {
+        assert_eq!(nearest_256((0x1e, 0x1e, 0x1e)), 234);
+        assert_eq!(nearest_256((250, 10, 10)), 196);
+        assert_eq!(nearest_16((250, 10, 10)), 9);
+        assert_eq!(nearest_16((10, 10, 200)), 4);
+        assert_eq!(nearest_16((10, 10, 60)), 0);
+    }
+}
@@ -0,0 +1,497 @@
+//! Parsing styles, colors and attributes from strings like
+//! `"bold underline bright-red on #1e1e1e"` and formatting them that way.
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use {Attr, Color, Style, UnderlineStyle};
+
+/// Canonical names of the named colors.
+pub const COLOR_NAMES: [(&str, Color); 16] = [
+    ("black", Color::Black),
+    ("red", Color::Red),
+    ("green", Color::Green),
+    ("yellow", Color::Yellow),
+    ("blue", Color::Blue),
+    ("magenta", Color::Magenta),
+    ("cyan", Color::Cyan),
+    ("white", Color::White),
+    ("bright-black", Color::BrightBlack),
+    ("bright-red", Color::BrightRed),
+    ("bright-green", Color::BrightGreen),
+    ("bright-yellow", Color::BrightYellow),
+    ("bright-blue", Color::BrightBlue),
+    ("bright-magenta", Color::BrightMagenta),
+    ("bright-cyan", Color::BrightCyan),
+    ("bright-white", Color::BrightWhite),
+];
+
+/// Canonical names of the attributes.
+pub const ATTR_NAMES: [(&str, Attr); 10] = [
+    ("plain", Attr::Plain),
+    ("bold", Attr::Bold),
+    ("dim", Attr::Dim),
+    ("italic", Attr::Italic),
+    ("underline", Attr::Underline),
+    ("blink", Attr::Blink),
+    ("reverse", Attr::Reverse),
+    ("secure", Attr::Secure),
+    ("strikethrough", Attr::Strikethrough),
+    ("overline", Attr::Overline),
+];
+
+/// Canonical names of the underline styles.
+pub const UNDERLINE_STYLE_NAMES: [(&str, UnderlineStyle); 5] = [
+    ("single", UnderlineStyle::Single),
+    ("double", UnderlineStyle::Double),
+    ("curly", UnderlineStyle::Curly),
+    ("dotted", UnderlineStyle::Dotted),
+    ("dashed", UnderlineStyle::Dashed),
+];
+
+/// Error returned when parsing a `Style`, `Color` or `Attr` fails. The
+/// offending part of the input is stored in each variant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// Not a color name, palette index or hex color.
+    UnknownColor(String),
+    /// Starts with `#`, but is not of the form `#rgb` or `#rrggbb`.
+    InvalidHexColor(String),
+    /// Not the name of an attribute.
+    UnknownAttr(String),
+    /// Not the name of an underline style.
+    UnknownUnderlineStyle(String),
+    /// The key of a `key=value` pair is unknown.
+    UnknownKey(String),
+    /// A word in a style that is neither a color nor an attribute nor any
+    /// other keyword.
+    UnknownWord(String),
+    /// A color is missing after `on` or in a `key=value` pair.
+    MissingColor(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::UnknownColor(ref s) => write!(
+                f,
+                "unknown color `{}` (expected a color name like `red` or `bright-red`, \
+                    a palette index or a hex color like `#ff8000`)",
+                s,
+            ),
+            ParseError::InvalidHexColor(ref s) => write!(
+                f,
+                "invalid hex color `{}` (expected `#rgb` or `#rrggbb`)",
+                s,
+            ),
+            ParseError::UnknownAttr(ref s) => write!(f, "unknown attribute `{}`", s),
+            ParseError::UnknownUnderlineStyle(ref s) => write!(
+                f,
+                "unknown underline style `{}` (expected `single`, `double`, `curly`, \
+                    `dotted` or `dashed`)",
+                s,
+            ),
+            ParseError::UnknownKey(ref s) => write!(
+                f,
+                "unknown key `{}` (expected `fg`, `bg`, `underline`, `underline-style` \
+                    or `underline-color`)",
+                s,
+            ),
+            ParseError::UnknownWord(ref s) => write!(
+                f,
+                "`{}` is neither a color nor an attribute",
+                s,
+            ),
+            ParseError::MissingColor(ref s) => write!(f, "expected a color after `{}`", s),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// Lowercases `s` and removes all `-` and `_`, so that `bright-red`,
+/// `bright_red` and `BrightRed` are all treated the same.
+fn normalize(s: &str) -> String {
+    s.chars().filter(|&c| c != '-' && c != '_').flat_map(char::to_lowercase).collect()
+}
+
+/// Looks up the (already normalized) `name` in the given table.
+fn lookup<T: Copy>(table: &[(&str, T)], name: &str) -> Option<T> {
+    table.iter().find(|&&(n, _)| normalize(n) == name).map(|&(_, v)| v)
+}
+
+fn parse_hex(s: &str) -> Result<Color, ParseError> {
+    let err = || ParseError::InvalidHexColor(s.to_string());
+    let digits = &s[1..];
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(err());
+    }
+
+    let channel = |i: usize, len: usize| u8::from_str_radix(&digits[i..i + len], 16).unwrap();
+    match digits.len() {
+        3 => Ok(Color::Rgb(channel(0, 1) * 17, channel(1, 1) * 17, channel(2, 1) * 17)),
+        6 => Ok(Color::Rgb(channel(0, 2), channel(2, 2), channel(4, 2))),
+        _ => Err(err()),
+    }
+}
+
+fn parse_underline_style(s: &str) -> Result<UnderlineStyle, ParseError> {
+    s.parse()
+}
+
+/// Parses the color after `on` or in a `key=value` pair.
+fn parse_color_after(s: Option<&str>, after: &str) -> Result<Color, ParseError> {
+    match s {
+        None | Some("") => Err(ParseError::MissingColor(after.to_string())),
+        Some(s) => s.parse(),
+    }
+}
+
+/// Accepts the names of the 16 named colors (`red`, `bright-red`, ...),
+/// palette indices (`208`), hex colors (`#ff8000` or `#f80`) and `none` for
+/// `Color::NotSet`. Case, `-` and `_` in names are ignored.
+///
+/// ```
+/// # use term_painter::Color;
+/// assert_eq!("bright-red".parse(), Ok(Color::BrightRed));
+/// assert_eq!("208".parse(), Ok(Color::Custom(208)));
+/// assert_eq!("#ff8000".parse(), Ok(Color::Rgb(255, 128, 0)));
+/// ```
+impl FromStr for Color {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with('#') {
+            return parse_hex(s);
+        }
+        if !s.is_empty() && s.chars().all(|c| c.is_ascii_digit()) {
+            return s.parse().map(Color::Custom)
+                .map_err(|_| ParseError::UnknownColor(s.to_string()));
+        }
+
+        let name = normalize(s);
+        if name == "none" {
+            return Ok(Color::NotSet);
+        }
+        lookup(&COLOR_NAMES, &name).ok_or_else(|| ParseError::UnknownColor(s.to_string()))
+    }
+}
+
+/// Accepts the names of the attributes (`bold`, `underline`, ...). `hidden`
+/// and `conceal` are accepted for `Secure`. Case, `-` and `_` are ignored.
+impl FromStr for Attr {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = normalize(s);
+        match &*name {
+            "hidden" | "conceal" => Ok(Attr::Secure),
+            _ => lookup(&ATTR_NAMES, &name).ok_or_else(|| ParseError::UnknownAttr(s.to_string())),
+        }
+    }
+}
+
+/// Accepts the names of the underline styles (`single`, `double`, `curly`,
+/// `dotted` and `dashed`). Case is ignored.
+impl FromStr for UnderlineStyle {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        lookup(&UNDERLINE_STYLE_NAMES, &normalize(s))
+            .ok_or_else(|| ParseError::UnknownUnderlineStyle(s.to_string()))
+    }
+}
+
+/// Parses a style from a whitespace separated list of words. Each word is
+/// one of:
+///
+/// - an attribute (see `Attr`'s `FromStr` impl) to enable it, or an attribute
+///   prefixed with `not-` to disable it (`not-underline`),
+/// - a color (see `Color`'s `FromStr` impl) to set the foreground color,
+/// - `on` followed by a color to set the background color,
+/// - an underline style followed by `-underline` (`curly-underline`),
+/// - a `key=value` pair with the keys `fg`, `bg` (both take a color),
+///   `underline` (takes an underline style), `underline-style` (like
+///   `underline`, but doesn't enable the underline attribute) or
+///   `underline-color`.
+///
+/// Later words override earlier ones. The empty string results in the
+/// default style.
+///
+/// ```
+/// # use term_painter::{Color, ToStyle, Style};
+/// let style: Style = "bold underline bright-red on #1e1e1e".parse().unwrap();
+/// assert_eq!(style, Color::BrightRed.bold().underline().bg(Color::Rgb(30, 30, 30)));
+///
+/// let style: Style = "fg=red bg=blue".parse().unwrap();
+/// assert_eq!(style, Color::Red.bg(Color::Blue));
+///
+/// let err = "bold redd".parse::<Style>().unwrap_err();
+/// assert_eq!(err.to_string(), "`redd` is neither a color nor an attribute");
+/// ```
+impl FromStr for Style {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut style = Style::default();
+        let mut words = s.split_whitespace();
+
+        while let Some(word) = words.next() {
+            // `key=value` pairs
+            if let Some(pos) = word.find('=') {
+                let (key, value) = (&word[..pos], &word[pos + 1..]);
+                match &*normalize(key) {
+                    "fg" => style.fg = parse_color_after(Some(value), word)?,
+                    "bg" => style.bg = parse_color_after(Some(value), word)?,
+                    "underlinecolor" => {
+                        style.underline_color = parse_color_after(Some(value), word)?;
+                    }
+                    "underline" => {
+                        style.set_underline(Some(true));
+                        style.set_underline_style(Some(parse_underline_style(value)?));
+                    }
+                    "underlinestyle" => {
+                        style.set_underline_style(Some(parse_underline_style(value)?));
+                    }
+                    _ => return Err(ParseError::UnknownKey(key.to_string())),
+                }
+                continue;
+            }
+
+            let name = normalize(word);
+            if name == "on" {
+                style.bg = parse_color_after(words.next(), word)?;
+            } else if let Some(attr_name) = name.strip_prefix("not") {
+                match attr_name.parse() {
+                    Ok(Attr::Plain) | Err(_) => {
+                        return Err(ParseError::UnknownAttr(word.to_string()));
+                    }
+                    Ok(attr) => style.set_attr(attr, Some(false)),
+                }
+            } else if let Ok(attr) = word.parse::<Attr>() {
+                style.set_attr(attr, Some(true));
+            } else if let Some(u) = name.find("underline")
+                .filter(|&pos| pos > 0 && name.len() == pos + "underline".len())
+                .and_then(|pos| lookup(&UNDERLINE_STYLE_NAMES, &name[..pos]))
+            {
+                style.set_underline(Some(true));
+                style.set_underline_style(Some(u));
+            } else {
+                style.fg = match word.parse() {
+                    Ok(c) => c,
+                    Err(ParseError::UnknownColor(_)) => {
+                        return Err(ParseError::UnknownWord(word.to_string()));
+                    }
+                    Err(e) => return Err(e),
+                };
+            }
+        }
+
+        Ok(style)
+    }
+}
+
+/// Returns the canonical name of `v` from the given table.
+fn name_of<T: Copy + PartialEq>(table: &[(&'static str, T)], v: T) -> &'static str {
+    table.iter().find(|&&(_, x)| x == v).map(|&(n, _)| n).unwrap()
+}
+
+/// Writes the canonical name of the color, which can be parsed again. Hex
+/// notation is used for RGB colors and `none` for `Color::NotSet`.
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Color::NotSet => f.pad("none"),
+            Color::Custom(c) => f.pad(&c.to_string()),
+            Color::Rgb(r, g, b) => f.pad(&format!("#{:02x}{:02x}{:02x}", r, g, b)),
+            c => f.pad(name_of(&COLOR_NAMES, c)),
+        }
+    }
+}
+
+impl fmt::Display for Attr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(name_of(&ATTR_NAMES, *self))
+    }
+}
+
+impl fmt::Display for UnderlineStyle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(name_of(&UNDERLINE_STYLE_NAMES, *self))
+    }
+}
+
+/// Writes the style in a canonical, human readable form that can be parsed
+/// into an equal `Style` again: first the attributes, then the foreground
+/// color and the background color (prefixed by `on`). The default style is
+/// written as `plain`.
+///
+/// ```
+/// # use term_painter::{Color, Style, ToStyle};
+/// let style = Color::Red.bg(Color::Blue).bold();
+/// assert_eq!(style.to_string(), "bold red on blue");
+/// assert_eq!(style.to_string().parse::<Style>(), Ok(style));
+/// ```
+impl fmt::Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut words = Vec::new();
+
+        for &(name, attr) in &ATTR_NAMES[1..] {
+            match (self.get_attr(attr), self.get_underline_style()) {
+                // Underline styles can be written in one word if the
+                // underline attribute is enabled
+                (Some(true), Some(u)) if attr == Attr::Underline => {
+                    words.push(format!("{}-underline", u));
+                    continue;
+                }
+                (Some(true), _) => words.push(name.to_string()),
+                (Some(false), _) => words.push(format!("not-{}", name)),
+                (None, _) => {}
+            }
+
+            if let (Attr::Underline, Some(u)) = (attr, self.get_underline_style()) {
+                words.push(format!("underline-style={}", u));
+            }
+        }
+
+        if self.fg != Color::NotSet {
+            words.push(self.fg.to_string());
+        }
+        if self.bg != Color::NotSet {
+            words.push(format!("on {}", self.bg));
+        }
+        if self.underline_color != Color::NotSet {
+            words.push(format!("underline-color={}", self.underline_color));
+        }
+
+        if words.is_empty() {
+            f.pad("plain")
+        } else {
+            f.pad(&words.join(" "))
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use Color::*;
+    use Attr::*;
+    use {ToStyle, Style, UnderlineStyle};
+    use super::ParseError;
+
+    fn style(s: &str) -> Result<Style, ParseError> {
+        s.parse()
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!("red".parse(), Ok(Red));
+        assert_eq!("Bright_Blue".parse(), Ok(BrightBlue));
+        assert_eq!("brightblue".parse(), Ok(BrightBlue));
+        assert_eq!("none".parse(), Ok(NotSet));
+        assert_eq!("0".parse(), Ok(Custom(0)));
+        assert_eq!("#1e1e1e".parse(), Ok(Rgb(30, 30, 30)));
+        assert_eq!("#F80".parse(), Ok(Rgb(255, 136, 0)));
+
+        assert_eq!("".parse::<::Color>(), Err(ParseError::UnknownColor("".into())));
+        assert_eq!("redd".parse::<::Color>(), Err(ParseError::UnknownColor("redd".into())));
+        assert_eq!("#12".parse::<::Color>(), Err(ParseError::InvalidHexColor("#12".into())));
+        assert_eq!("#12345g".parse::<::Color>(), Err(ParseError::InvalidHexColor("#12345g".into())));
+        assert_eq!("#ü1".parse::<::Color>(), Err(ParseError::InvalidHexColor("#ü1".into())));
+        assert!("99999999999".parse::<::Color>().is_err());
+    }
+
+    #[test]
+    fn attrs() {
+        assert_eq!("bold".parse(), Ok(Bold));
+        assert_eq!("Strike-Through".parse(), Ok(Strikethrough));
+        assert_eq!("hidden".parse(), Ok(Secure));
+        assert_eq!("boldd".parse::<::Attr>(), Err(ParseError::UnknownAttr("boldd".into())));
+    }
+
+    #[test]
+    fn styles() {
+        assert_eq!(style(""), Ok(Style::default()));
+        assert_eq!(style("  plain "), Ok(Style::default()));
+        assert_eq!(style("bold red on blue"), Ok(Red.bold().bg(Blue)));
+        assert_eq!(style("on blue red bold"), Ok(Red.bold().bg(Blue)));
+        assert_eq!(style("fg=red bg=#000"), Ok(Red.bg(Rgb(0, 0, 0))));
+        assert_eq!(style("red blue"), Ok(Blue.to_style()));
+        assert_eq!(style("196 on 17"), Ok(Custom(196).bg(Custom(17))));
+        assert_eq!(style("not-underline italic"), Ok(Plain.not_underline().italic()));
+        assert_eq!(style("not-hidden").map(|s| s.get_secure()), Ok(Some(false)));
+        assert_eq!(
+            style("curly-underline underline-color=red"),
+            Ok(Plain.underline_style(UnderlineStyle::Curly).underline_color(Red)),
+        );
+        assert_eq!(
+            style("underline=dotted"),
+            Ok(Plain.underline_style(UnderlineStyle::Dotted)),
+        );
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(NotSet.to_string(), "none");
+        assert_eq!(BrightMagenta.to_string(), "bright-magenta");
+        assert_eq!(Custom(208).to_string(), "208");
+        assert_eq!(Rgb(0x1e, 0xff, 0).to_string(), "#1eff00");
+        assert_eq!(Strikethrough.to_string(), "strikethrough");
+        assert_eq!(
+            format!("{:>8}|{:<4}|{:^9}|", Red, Custom(7), Rgb(1, 2, 3)),
+            "     red|7   | #010203 |",
+        );
+        assert_eq!(format!("{:>8}|", Bold), "    bold|");
+
+        assert_eq!(Style::default().to_string(), "plain");
+        assert_eq!(Red.bold().bg(Blue).to_string(), "bold red on blue");
+        assert_eq!(Plain.not_underline().dim().to_string(), "dim not-underline");
+        assert_eq!(
+            Plain.underline_style(UnderlineStyle::Curly).underline_color(Red).to_string(),
+            "curly-underline underline-color=red",
+        );
+    }
+
+    #[test]
+    fn display_roundtrip() {
+        let mut dashed = Plain.not_underline();
+        dashed.set_underline_style(Some(UnderlineStyle::Dashed));
+        let mut dotted = Plain.bold();
+        dotted.set_underline_style(Some(UnderlineStyle::Dotted));
+
+        let styles = [
+            Style::default(),
+            Plain.bg(Custom(3)),
+            Rgb(1, 2, 3).bg(BrightWhite).italic().blink().reverse(),
+            Plain.secure().strikethrough().overline().not_underline(),
+            Plain.underline_style(UnderlineStyle::Double).underline_color(Rgb(9, 9, 9)),
+            dashed,
+            dotted,
+        ];
+
+        for &s in &styles {
+            assert_eq!(style(&s.to_string()), Ok(s), "{}", s);
+        }
+
+        for &(_, c) in &super::COLOR_NAMES {
+            assert_eq!(c.to_string().parse(), Ok(c));
+        }
+        for &(_, a) in &super::ATTR_NAMES {
+            assert_eq!(a.to_string().parse(), Ok(a));
+        }
+    }
+
+    #[test]
+    fn style_errors() {
+        assert_eq!(style("bold on"), Err(ParseError::MissingColor("on".into())));
+        assert_eq!(style("fg="), Err(ParseError::MissingColor("fg=".into())));
+        assert_eq!(style("on bold"), Err(ParseError::UnknownColor("bold".into())));
+        assert_eq!(style("size=3"), Err(ParseError::UnknownKey("size".into())));
+        assert_eq!(style("not-red"), Err(ParseError::UnknownAttr("not-red".into())));
+        assert_eq!(style("wavy-underline"), Err(ParseError::UnknownWord("wavy-underline".into())));
+        assert_eq!(style("underline=wavy"), Err(ParseError::UnknownUnderlineStyle("wavy".into())));
+        assert_eq!(style("#12"), Err(ParseError::InvalidHexColor("#12".into())));
+    }
+}
@@ -0,0 +1,345 @@
+//! `Serialize` and `Deserialize` implementations, enabled by the `serde`
+//! feature.
+//!
+//! Colors, attributes and underline styles are (de)serialized as strings,
+//! using their `Display` and `FromStr` implementations. Colors can also be
+//! deserialized from a palette index (`208`) or an `[r, g, b]` array.
+//!
+//! Styles are serialized in a structured form: a map with the keys `fg`,
+//! `bg`, `underline_color`, `underline_style` and one key per attribute
+//! (`bold`, `dim`, ...). Only the properties that are set are written and
+//! missing keys are treated as not set, so the tri-state of attributes is
+//! preserved: a missing `bold` key is `None`, `bold: false` is
+//! `Some(false)`. Styles can be deserialized from the structured form or the
+//! compact string form (`"bold red on blue"`). To serialize them in the
+//! compact form, use the `compact_style` module.
+//!
+//! Themes are (de)serialized as a map from names to styles, using the compact
+//! form for serialization.
+//!
+//! As colors and styles can be deserialized from several forms, this relies
+//! on `Deserializer::deserialize_any`. Thus, only self-describing formats
+//! (like JSON, TOML or YAML) are supported, not ones like bincode or
+//! postcard.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde_crate::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_crate::ser::{Serialize, SerializeMap, Serializer};
+
+use {Attr, Color, Style, Theme, UnderlineStyle};
+use parse::ATTR_NAMES;
+
+/// Visitor for types that are deserialized from a string via `FromStr`.
+struct FromStrVisitor<T>(&'static str, ::std::marker::PhantomData<T>);
+
+impl<T> FromStrVisitor<T> {
+    fn new(expecting: &'static str) -> Self {
+        FromStrVisitor(expecting, ::std::marker::PhantomData)
+    }
+}
+
+impl<'de, T> Visitor<'de> for FromStrVisitor<T>
+    where T: FromStr,
+          T::Err: fmt::Display,
+{
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.0)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+        v.parse().map_err(E::custom)
+    }
+}
+
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+struct ColorVisitor;
+
+impl<'de> Visitor<'de> for ColorVisitor {
+    type Value = Color;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a color name, a palette index, a hex color or an [r, g, b] array")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Color, E> {
+        v.parse().map_err(E::custom)
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Color, E> {
+        if v > u32::MAX as u64 {
+            return Err(E::invalid_value(de::Unexpected::Unsigned(v), &self));
+        }
+        Ok(Color::Custom(v as u32))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Color, E> {
+        if v < 0 {
+            return Err(E::invalid_value(de::Unexpected::Signed(v), &self));
+        }
+        self.visit_u64(v as u64)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Color, A::Error> {
+        let mut next = |i| {
+            seq.next_element::<u8>()?.ok_or_else(|| de::Error::invalid_length(i, &self))
+        };
+        let (r, g, b) = (next(0)?, next(1)?, next(2)?);
+        if seq.next_element::<de::IgnoredAny>()?.is_some() {
+            return Err(de::Error::invalid_length(4, &self));
+        }
+        Ok(Color::Rgb(r, g, b))
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ColorVisitor)
+    }
+}
+
+impl Serialize for Attr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Attr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(FromStrVisitor::new("an attribute name"))
+    }
+}
+
+impl Serialize for UnderlineStyle {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for UnderlineStyle {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(FromStrVisitor::new("an underline style"))
+    }
+}
+
+impl Serialize for Style {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+
+        if self.fg != Color::NotSet {
+            map.serialize_entry("fg", &self.fg)?;
+        }
+        if self.bg != Color::NotSet {
+            map.serialize_entry("bg", &self.bg)?;
+        }
+        if self.underline_color != Color::NotSet {
+            map.serialize_entry("underline_color", &self.underline_color)?;
+        }
+        if let Some(u) = self.get_underline_style() {
+            map.serialize_entry("underline_style", &u)?;
+        }
+        for &(name, attr) in &ATTR_NAMES[1..] {
+            if let Some(v) = self.get_attr(attr) {
+                map.serialize_entry(name, &v)?;
+            }
+        }
+
+        map.end()
+    }
+}
+
+const STYLE_FIELDS: &[&str] = &[
+    "fg", "bg", "underline_color", "underline_style", "bold", "dim", "italic",
+    "underline", "blink", "reverse", "secure", "strikethrough", "overline",
+];
+
+struct StyleVisitor;
+
+impl<'de> Visitor<'de> for StyleVisitor {
+    type Value = Style;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a style string like \"bold red on blue\" or a map of style properties")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Style, E> {
+        v.parse().map_err(E::custom)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Style, A::Error> {
+        let mut style = Style::default();
+
+        while let Some(key) = map.next_key::<String>()? {
+            match &*key {
+                "fg" => style.fg = map.next_value()?,
+                "bg" => style.bg = map.next_value()?,
+                "underline_color" => style.underline_color = map.next_value()?,
+                "underline_style" => style.set_underline_style(map.next_value()?),
+                _ => {
+                    let attr = ATTR_NAMES[1..].iter()
+                        .find(|&&(name, _)| name == key)
+                        .map(|&(_, attr)| attr)
+                        .ok_or_else(|| de::Error::unknown_field(&key, STYLE_FIELDS))?;
+                    style.set_attr(attr, map.next_value()?);
+                }
+            }
+        }
+
+        Ok(style)
+    }
+}
+
+impl<'de> Deserialize<'de> for Style {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(StyleVisitor)
+    }
+}
+
+impl Serialize for Theme {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter().map(|(name, style)| (name, style.to_string())))
+    }
+}
+
+struct ThemeVisitor;
+
+impl<'de> Visitor<'de> for ThemeVisitor {
+    type Value = Theme;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a map from names to styles")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Theme, A::Error> {
+        let mut theme = Theme::new();
+        while let Some((name, style)) = map.next_entry::<String, Style>()? {
+            theme.insert(name, style);
+        }
+        Ok(theme)
+    }
+}
+
+impl<'de> Deserialize<'de> for Theme {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(ThemeVisitor)
+    }
+}
+
+/// (De)serializes a `Style` in the compact string form (like
+/// `"bold red on blue"`). Use it with serde's `with` attribute:
+///
+/// ```ignore
+/// #[derive(Serialize, Deserialize)]
+/// struct Theme {
+///     #[serde(with = "term_painter::serde::compact_style")]
+///     error: Style,
+/// }
+/// ```
+pub mod compact_style {
+    use serde_crate::{Deserialize, Deserializer, Serializer};
+    use Style;
+
+    pub fn serialize<S: Serializer>(style: &Style, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(style)
+    }
+
+    /// Accepts the structured form, too.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Style, D::Error> {
+        Style::deserialize(deserializer)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    extern crate serde_json;
+
+    use Color::*;
+    use Attr::*;
+    use {Color, Style, Theme, ToStyle, UnderlineStyle};
+
+    fn to_json<T: ::serde_crate::Serialize>(v: &T) -> String {
+        serde_json::to_string(v).unwrap()
+    }
+
+    fn from_json<T: ::serde_crate::de::DeserializeOwned>(s: &str) -> Result<T, String> {
+        serde_json::from_str(s).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(to_json(&BrightRed), r#""bright-red""#);
+        assert_eq!(to_json(&Custom(208)), r#""208""#);
+        assert_eq!(to_json(&Rgb(1, 2, 255)), r##""#0102ff""##);
+
+        assert_eq!(from_json::<Color>(r#""bright-red""#), Ok(BrightRed));
+        assert_eq!(from_json::<Color>(r#""208""#), Ok(Custom(208)));
+        assert_eq!(from_json::<Color>("208"), Ok(Custom(208)));
+        assert_eq!(from_json::<Color>("[1, 2, 255]"), Ok(Rgb(1, 2, 255)));
+        assert!(from_json::<Color>("[1, 2]").is_err());
+        assert!(from_json::<Color>("[1, 2, 3, 4]").is_err());
+        assert!(from_json::<Color>("-1").is_err());
+        assert!(from_json::<Color>(r#""redd""#).unwrap_err().contains("unknown color `redd`"));
+    }
+
+    #[test]
+    fn attrs() {
+        assert_eq!(to_json(&Strikethrough), r#""strikethrough""#);
+        assert_eq!(from_json::<::Attr>(r#""bold""#), Ok(Bold));
+        assert_eq!(to_json(&UnderlineStyle::Curly), r#""curly""#);
+        assert_eq!(from_json::<UnderlineStyle>(r#""dotted""#), Ok(UnderlineStyle::Dotted));
+    }
+
+    #[test]
+    fn styles() {
+        let style = Red.bg(Blue).bold().not_underline();
+        let json = r#"{"fg":"red","bg":"blue","bold":true,"underline":false}"#;
+        assert_eq!(to_json(&style), json);
+        assert_eq!(from_json::<Style>(json), Ok(style));
+        assert_eq!(from_json::<Style>(r#""bold red on blue not-underline""#), Ok(style));
+
+        assert_eq!(to_json(&Style::default()), "{}");
+        assert_eq!(from_json::<Style>("{}"), Ok(Style::default()));
+        assert_eq!(from_json::<Style>(r#"{"bold":null}"#), Ok(Style::default()));
+
+        let curly = Plain.underline_style(UnderlineStyle::Curly).underline_color(Rgb(255, 0, 0));
+        assert_eq!(from_json::<Style>(&to_json(&curly)), Ok(curly));
+
+        assert!(from_json::<Style>(r#"{"size":3}"#).unwrap_err().contains("unknown field `size`"));
+    }
+
+    #[test]
+    fn themes() {
+        let theme: Theme = "error = bold red\npath = underline".parse().unwrap();
+        let json = r#"{"error":"bold red","path":"underline"}"#;
+        assert_eq!(to_json(&theme), json);
+        assert_eq!(from_json::<Theme>(json).unwrap(), theme);
+        assert_eq!(
+            from_json::<Theme>(r#"{"error":{"fg":"red","bold":true},"path":"underline"}"#).unwrap(),
+            theme,
+        );
+    }
+
+    #[test]
+    fn compact() {
+        #[derive(Debug, PartialEq)]
+        struct Wrapper(Style);
+
+        impl ::serde_crate::Serialize for Wrapper {
+            fn serialize<S: ::serde_crate::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+                super::compact_style::serialize(&self.0, s)
+            }
+        }
+
+        let style = Red.bold();
+        assert_eq!(to_json(&Wrapper(style)), r#""bold red""#);
+    }
+}
@@ -0,0 +1,267 @@
+//! Parsing text with ANSI escape sequences back into styled spans.
+
+use strip::Stripper;
+use {Color, Style, UnderlineStyle};
+
+/// Parses text containing SGR escape sequences (e.g. captured from a
+/// program like `cargo` or `git`) into spans of text and the style they are
+/// displayed with:
+///
+/// ```
+/// # use term_painter::{parse_ansi, Color, ToStyle};
+/// let spans: Vec<_> = parse_ansi("\x1b[1;31merror\x1b[0m: \x1b[38;5;208mfoo").collect();
+/// assert_eq!(spans, vec![
+///     (Color::Red.bold(), "error"),
+///     (Color::NotSet.to_style(), ": "),
+///     (Color::Custom(208).to_style(), "foo"),
+/// ]);
+/// ```
+///
+/// The styles describe the full state relative to the terminal's defaults:
+/// attributes that are turned off are unset (`None`) and default colors are
+/// `Color::NotSet`. Palette colors (`38;5;n`) become `Color::Custom` (or the
+/// named color for the first 16 entries) and truecolor (`38;2;r;g;b`) becomes
+/// `Color::Rgb`, both in the `;` and the `:` notation. All other escape
+/// sequences (like hyperlinks or cursor movement) are skipped, as are unknown
+/// SGR codes. Empty spans are never yielded.
+pub fn parse_ansi(s: &str) -> AnsiSpans<'_> {
+    AnsiSpans {
+        rest: s,
+        style: Style::default(),
+    }
+}
+
+/// Iterator over the styled spans of a string, created by `parse_ansi`.
+#[derive(Debug, Clone)]
+pub struct AnsiSpans<'a> {
+    rest: &'a str,
+    style: Style,
+}
+
+impl<'a> Iterator for AnsiSpans<'a> {
+    type Item = (Style, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.rest.starts_with('\x1b') {
+            let len = sequence_len(self.rest);
+            let seq = &self.rest[..len];
+            if let Some(params) = seq.strip_prefix("\x1b[").and_then(|s| s.strip_suffix('m')) {
+                apply_sgr(&mut self.style, params);
+            }
+            self.rest = &self.rest[len..];
+        }
+
+        if self.rest.is_empty() {
+            return None;
+        }
+
+        let end = self.rest.find('\x1b').unwrap_or(self.rest.len());
+        let (text, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        Some((self.style, text))
+    }
+}
+
+/// Returns the length of the escape sequence `s` starts with. Unterminated
+/// sequences span the rest of `s`, up to the next ESC, which always starts a
+/// new sequence.
+fn sequence_len(s: &str) -> usize {
+    let mut stripper = Stripper::new();
+    for (i, b) in s.bytes().enumerate() {
+        if i > 0 && b == 0x1b {
+            return i;
+        }
+        if stripper.keep(b) {
+            // `b` is the first byte after the (malformed) sequence
+            return i;
+        }
+        if !stripper.in_sequence() {
+            return i + 1;
+        }
+    }
+
+    s.len()
+}
+
+/// Applies the SGR parameters `params` (like `1;38;5;208`) to `style`.
+fn apply_sgr(style: &mut Style, params: &str) {
+    let mut params = params.split(';');
+
+    while let Some(param) = params.next() {
+        // Sub parameters are separated by `:`, like in `4:3` or `38:2::1:2:3`
+        let mut sub = param.split(':');
+        let code = match sub.next().unwrap_or("") {
+            "" => 0,
+            code => match code.parse::<u32>() {
+                Ok(code) => code,
+                Err(_) => continue,
+            },
+        };
+
+        match code {
+            0 => *style = Style::default(),
+            1 => style.set_bold(Some(true)),
+            2 => style.set_dim(Some(true)),
+            3 => style.set_italic(Some(true)),
+            4 => {
+                let u = match sub.next() {
+                    None => Some(UnderlineStyle::Single),
+                    Some("0") => None,
+                    Some("2") => Some(UnderlineStyle::Double),
+                    Some("3") => Some(UnderlineStyle::Curly),
+                    Some("4") => Some(UnderlineStyle::Dotted),
+                    Some("5") => Some(UnderlineStyle::Dashed),
+                    Some(_) => Some(UnderlineStyle::Single),
+                };
+                style.set_underline(u.map(|_| true));
+                style.set_underline_style(u.filter(|&u| u != UnderlineStyle::Single));
+            }
+            5 | 6 => style.set_blink(Some(true)),
+            7 => style.set_reverse(Some(true)),
+            8 => style.set_secure(Some(true)),
+            9 => style.set_strikethrough(Some(true)),
+            21 => {
+                style.set_underline(Some(true));
+                style.set_underline_style(Some(UnderlineStyle::Double));
+            }
+            22 => {
+                style.set_bold(None);
+                style.set_dim(None);
+            }
+            23 => style.set_italic(None),
+            24 => {
+                style.set_underline(None);
+                style.set_underline_style(None);
+            }
+            25 => style.set_blink(None),
+            27 => style.set_reverse(None),
+            28 => style.set_secure(None),
+            29 => style.set_strikethrough(None),
+            30..=37 => style.fg = Color::from_index(code - 30),
+            38 => style.fg = extended_color(&mut sub, &mut params),
+            39 => style.fg = Color::NotSet,
+            40..=47 => style.bg = Color::from_index(code - 40),
+            48 => style.bg = extended_color(&mut sub, &mut params),
+            49 => style.bg = Color::NotSet,
+            53 => style.set_overline(Some(true)),
+            55 => style.set_overline(None),
+            58 => style.underline_color = extended_color(&mut sub, &mut params),
+            59 => style.underline_color = Color::NotSet,
+            90..=97 => style.fg = Color::from_index(code - 90 + 8),
+            100..=107 => style.bg = Color::from_index(code - 100 + 8),
+            _ => {}
+        }
+    }
+}
+
+/// Parses the color of the codes 38, 48 and 58. It's given either as sub
+/// parameters (`38:5:n`, `38:2:r:g:b` or `38:2:id:r:g:b`) or as the following
+/// parameters (`38;5;n` or `38;2;r;g;b`). Invalid colors become `NotSet`.
+fn extended_color<'a, I, J>(sub: &mut I, params: &mut J) -> Color
+    where I: Iterator<Item = &'a str>,
+          J: Iterator<Item = &'a str>,
+{
+    let mut sub = sub.peekable();
+    let colon = sub.peek().is_some();
+    let args: Vec<&str> = if colon {
+        sub.collect()
+    } else {
+        match params.next() {
+            Some("5") => vec!["5"].into_iter().chain(params.next()).collect(),
+            Some("2") => vec!["2"].into_iter().chain(params.take(3)).collect(),
+            _ => return Color::NotSet,
+        }
+    };
+
+    let num = |s: &str| s.parse::<u8>().ok();
+    match &args[..] {
+        ["5", n] => num(n).map(|n| Color::from_index(n as u32)),
+        ["2", r, g, b] | ["2", _, r, g, b] => {
+            match (num(r), num(g), num(b)) {
+                (Some(r), Some(g), Some(b)) => Some(Color::Rgb(r, g, b)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }.unwrap_or(Color::NotSet)
+}
+
+
+#[cfg(test)]
+mod test {
+    use Color::*;
+    use Attr::*;
+    use {ColorChoice, Style, ToStyle, UnderlineStyle};
+    use super::parse_ansi;
+
+    fn spans(s: &str) -> Vec<(Style, &str)> {
+        parse_ansi(s).collect()
+    }
+
+    #[test]
+    fn basic() {
+        assert_eq!(spans(""), vec![]);
+        assert_eq!(spans("plain"), vec![(Plain.to_style(), "plain")]);
+        assert_eq!(
+            spans("\x1b[31ma\x1b[1mb\x1b[22;39mc\x1b[m"),
+            vec![(Red.to_style(), "a"), (Red.bold(), "b"), (Plain.to_style(), "c")],
+        );
+        assert_eq!(
+            spans("\x1b[94;103;3;9;53mx\x1b[0;7;8;5my"),
+            vec![
+                (BrightBlue.bg(BrightYellow).italic().strikethrough().overline(), "x"),
+                (Reverse.secure().blink(), "y"),
+            ],
+        );
+
+        // Other sequences and unknown codes are skipped
+        assert_eq!(
+            spans("\x1b]8;;https://x.org\x1b\\\x1b[2K\x1b[32;99mlink\x1b]8;;\x07"),
+            vec![(Green.to_style(), "link")],
+        );
+    }
+
+    #[test]
+    fn extended_colors() {
+        assert_eq!(spans("\x1b[38;5;208mx")[0].0, Custom(208).to_style());
+        assert_eq!(spans("\x1b[48;2;1;2;3;1mx")[0].0, Plain.bg(Rgb(1, 2, 3)).bold());
+        assert_eq!(spans("\x1b[38:5:17mx")[0].0, Custom(17).to_style());
+        assert_eq!(spans("\x1b[38:2::1:2:3mx")[0].0, Rgb(1, 2, 3).to_style());
+        assert_eq!(spans("\x1b[38:2:1:2:3mx")[0].0, Rgb(1, 2, 3).to_style());
+        assert_eq!(spans("\x1b[58;5;1mx")[0].0, Plain.underline_color(Red));
+        assert_eq!(spans("\x1b[38;5;9mx")[0].0, BrightRed.to_style());
+
+        // Invalid colors are ignored
+        assert_eq!(spans("\x1b[38;5;300;1mx")[0].0, Bold.to_style());
+        assert_eq!(spans("\x1b[38;7;1mx")[0].0, Bold.to_style());
+    }
+
+    #[test]
+    fn underlines() {
+        assert_eq!(spans("\x1b[4mx")[0].0, Underline.to_style());
+        assert_eq!(spans("\x1b[4:3mx")[0].0, Plain.underline_style(UnderlineStyle::Curly));
+        assert_eq!(spans("\x1b[21mx")[0].0, Plain.underline_style(UnderlineStyle::Double));
+        assert_eq!(spans("\x1b[4:3;4:0mx")[0].0, Plain.to_style());
+        assert_eq!(spans("\x1b[4:5;24mx")[0].0, Plain.to_style());
+    }
+
+    #[test]
+    fn roundtrip() {
+        let style = Rgb(1, 2, 3).bg(Custom(208)).bold().dim().italic()
+            .underline_style(UnderlineStyle::Dotted).underline_color(Blue);
+        let s = ColorChoice::Always.with(|| style.paint("x").ansi().to_string());
+        assert_eq!(spans(&s), vec![(style, "x")]);
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(spans("\x1b[31\nx"), vec![(Plain.to_style(), "\nx")]);
+        assert_eq!(spans("a\x1b[1"), vec![(Plain.to_style(), "a")]);
+        assert_eq!(spans("\x1b[1;?m\x1bü"), vec![(Bold.to_style(), "ü")]);
+
+        // An ESC ends a malformed or unterminated sequence
+        assert_eq!(spans("\x1b[1\x1b[32mgreen"), vec![(Green.to_style(), "green")]);
+        assert_eq!(spans("\x1b]8;;u\x1b[1mbold"), vec![(Bold.to_style(), "bold")]);
+        assert_eq!(spans("\x1b]8;;u\x1b\\link"), vec![(Plain.to_style(), "link")]);
+    }
+}
@@ -0,0 +1,249 @@
+//! Removing escape sequences from text.
+
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+const ESC: u8 = 0x1b;
+const BEL: u8 = 0x07;
+
+/// Removes all escape sequences from `s`: CSI sequences (which include the
+/// SGR sequences used for styling), OSC sequences (which include
+/// hyperlinks), other string sequences (DCS, SOS, PM and APC) and simple
+/// two-character escapes. Everything else, including control characters like
+/// `\n` and `\t`, is kept.
+///
+/// ```
+/// # use term_painter::{strip_ansi, Color, ColorChoice, ToStyle};
+/// let s = ColorChoice::Always.with(|| Color::Red.bold().paint("error").ansi().to_string());
+/// assert_eq!(strip_ansi(&s), "error");
+/// ```
+///
+/// Malformed sequences are removed up to the character that makes them
+/// invalid; an unterminated sequence at the end of `s` is removed entirely.
+/// If `s` contains no escape character, it's returned as is without
+/// allocating.
+pub fn strip_ansi(s: &str) -> Cow<'_, str> {
+    if !s.as_bytes().contains(&ESC) {
+        return Cow::Borrowed(s);
+    }
+
+    let mut stripper = Stripper::new();
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        // Escape sequences only consist of ASCII characters, so the first
+        // byte of a character decides whether it belongs to one.
+        let mut buf = [0; 4];
+        if stripper.keep(c.encode_utf8(&mut buf).as_bytes()[0]) {
+            out.push(c);
+        }
+    }
+
+    Cow::Owned(out)
+}
+
+/// A writer that removes escape sequences (see `strip_ansi`) from everything
+/// written to it before passing it on to the inner writer. Sequences can be
+/// split across multiple writes.
+///
+/// ```
+/// # use term_painter::StripWriter;
+/// use std::io::Write;
+///
+/// let mut w = StripWriter::new(Vec::new());
+/// w.write_all(b"\x1b[3").unwrap();
+/// w.write_all(b"1mred\x1b[0m").unwrap();
+/// assert_eq!(w.into_inner(), b"red");
+/// ```
+pub struct StripWriter<W> {
+    inner: W,
+    stripper: Stripper,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> StripWriter<W> {
+    /// Creates a writer that passes everything but escape sequences on to
+    /// `inner`.
+    pub fn new(inner: W) -> Self {
+        StripWriter {
+            inner,
+            stripper: Stripper::new(),
+            buf: Vec::new(),
+        }
+    }
+
+    /// Returns a reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Returns the inner writer. An unterminated escape sequence at the end
+    /// of the written data is discarded.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for StripWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.clear();
+        for &b in buf {
+            if self.stripper.keep(b) {
+                self.buf.push(b);
+            }
+        }
+        self.inner.write_all(&self.buf)?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Where in an escape sequence the stripper is. The grammar follows ECMA-48,
+/// but only the 7-bit forms of the sequences (starting with `ESC`) are
+/// recognized, as the 8-bit forms conflict with UTF-8.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum State {
+    /// Not in an escape sequence.
+    Ground,
+    /// After `ESC`.
+    Escape,
+    /// After `ESC` and at least one intermediate byte (like in `ESC ( B`).
+    EscapeIntermediate,
+    /// In a CSI sequence (`ESC [`), waiting for the final byte.
+    Csi,
+    /// In a string sequence (OSC, DCS, SOS, PM or APC), waiting for `BEL` or
+    /// the string terminator `ESC \`.
+    Str,
+    /// After `ESC` in a string sequence.
+    StrEscape,
+}
+
+/// State machine deciding byte by byte whether the byte belongs to an
+/// escape sequence.
+#[derive(Debug)]
+pub struct Stripper {
+    state: State,
+}
+
+impl Stripper {
+    pub fn new() -> Self {
+        Stripper { state: State::Ground }
+    }
+
+    /// Returns whether the last byte fed was not the last byte of an escape
+    /// sequence, so that the sequence continues.
+    pub fn in_sequence(&self) -> bool {
+        self.state != State::Ground
+    }
+
+    /// Feeds the next byte and returns whether it should be kept, i.e. it
+    /// doesn't belong to an escape sequence.
+    pub fn keep(&mut self, b: u8) -> bool {
+        let next = match (self.state, b) {
+            (State::Ground, ESC) => State::Escape,
+            (State::Ground, _) => return true,
+
+            (State::Escape, b'[') => State::Csi,
+            (State::Escape, b']') | (State::Escape, b'P') | (State::Escape, b'X')
+                | (State::Escape, b'^') | (State::Escape, b'_') => State::Str,
+            (State::Escape, 0x20..=0x2f) => State::EscapeIntermediate,
+            (State::Escape, 0x30..=0x7e) => State::Ground,
+
+            (State::EscapeIntermediate, 0x20..=0x2f) => State::EscapeIntermediate,
+            (State::EscapeIntermediate, 0x30..=0x7e) => State::Ground,
+
+            // Parameter and intermediate bytes, then the final byte
+            (State::Csi, 0x20..=0x3f) => State::Csi,
+            (State::Csi, 0x40..=0x7e) => State::Ground,
+
+            (State::Str, BEL) => State::Ground,
+            (State::Str, ESC) => State::StrEscape,
+            (State::Str, _) => State::Str,
+
+            (State::StrEscape, b'\\') => State::Ground,
+
+            // A new escape sequence interrupts the current one
+            (_, ESC) => State::Escape,
+
+            // The string was unterminated and the `ESC` started a new sequence
+            (State::StrEscape, _) => {
+                self.state = State::Escape;
+                return self.keep(b);
+            }
+
+            // Anything else makes the sequence invalid. It's dropped and the
+            // byte is treated as regular text.
+            (_, _) => {
+                self.state = State::Ground;
+                return self.keep(b);
+            }
+        };
+
+        self.state = next;
+        false
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+    use std::io::Write;
+
+    use super::{strip_ansi, StripWriter};
+
+    #[test]
+    fn sequences() {
+        assert!(matches!(strip_ansi("plain\ttext\n"), Cow::Borrowed("plain\ttext\n")));
+        assert_eq!(strip_ansi("\x1b[31;1mred\x1b[0m"), "red");
+        assert_eq!(strip_ansi("\x1b[38;2;1;2;3mrgb\x1b[4:3m ü\x1b[m"), "rgb ü");
+        assert_eq!(strip_ansi("\x1b]8;;https://x.org\x1b\\link\x1b]8;;\x1b\\"), "link");
+        assert_eq!(strip_ansi("\x1b]0;title\x07text"), "text");
+        assert_eq!(strip_ansi("\x1bP+q\x1b\\a\x1b_apc\x1b\\b"), "ab");
+        assert_eq!(strip_ansi("\x1b7save\x1b8 \x1b(Bcharset"), "save charset");
+        assert_eq!(strip_ansi("\x1b[?25lhidden cursor\x1b[2J"), "hidden cursor");
+    }
+
+    #[test]
+    fn malformed() {
+        // Invalid bytes end the sequence and are kept
+        assert_eq!(strip_ansi("\x1b[31\nnext"), "\nnext");
+        assert_eq!(strip_ansi("\x1bü"), "ü");
+        assert_eq!(strip_ansi("\x1b[1\x1b[32mgreen"), "green");
+        assert_eq!(strip_ansi("\x1b]8;;url\x1b[1mbold"), "bold");
+
+        // Unterminated sequences at the end are removed
+        assert_eq!(strip_ansi("a\x1b"), "a");
+        assert_eq!(strip_ansi("a\x1b[38;5"), "a");
+        assert_eq!(strip_ansi("a\x1b]8;;https://x.org"), "a");
+        assert_eq!(strip_ansi("a\x1b( "), "a");
+    }
+
+    #[test]
+    fn partial_writes() {
+        let input = "\x1b]8;;u\x1b\\x\x1b[1;31my\x1b[0m\x1b(Bz";
+
+        // Split the input at every possible position and byte by byte
+        for i in 0..input.len() {
+            let mut w = StripWriter::new(Vec::new());
+            w.write_all(&input.as_bytes()[..i]).unwrap();
+            w.write_all(&input.as_bytes()[i..]).unwrap();
+            assert_eq!(w.into_inner(), b"xyz");
+        }
+
+        let mut w = StripWriter::new(Vec::new());
+        for b in input.bytes() {
+            w.write_all(&[b]).unwrap();
+        }
+        w.write_all("ä\x1b[".as_bytes()).unwrap();
+        assert_eq!(w.get_ref(), "xyzä".as_bytes());
+    }
+}
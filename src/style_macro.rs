@@ -0,0 +1,278 @@
+//! The `style!` macro, which parses styles at compile time.
+
+use Color;
+
+/// Creates a `Style` from the same words `Style`'s `FromStr` impl accepts,
+/// but checked at compile time. The style is a constant, so the macro can be
+/// used in `const` and `static` items:
+///
+/// ```
+/// #[macro_use]
+/// extern crate term_painter;
+///
+/// use term_painter::{Color, Style, ToStyle};
+///
+/// const ERROR: Style = style!(bold bright-red on #1e1e1e);
+/// static WARNING: Style = style!(fg=yellow curly-underline underline-color=208);
+///
+/// # fn main() {
+/// assert_eq!(ERROR, "bold bright-red on #1e1e1e".parse().unwrap());
+/// assert_eq!(WARNING, Color::Yellow.underline_style(term_painter::UnderlineStyle::Curly)
+///     .underline_color(Color::Custom(208)));
+/// println!("{}", ERROR.paint("error"));
+/// # }
+/// ```
+///
+/// Only the canonical, lowercase spelling of names (as written by `Style`'s
+/// `Display` impl) is accepted, plus `hidden` and `conceal` for `secure`.
+///
+/// Hex colors whose digits look like a float with an exponent (like
+/// `#0000ee` or `#12345e`) are rejected by Rust's tokenizer. Write them as a
+/// string literal instead, which works for all hex colors:
+///
+/// ```
+/// # #[macro_use] extern crate term_painter;
+/// # use term_painter::{Color, ToStyle};
+/// # fn main() {
+/// assert_eq!(style!(on "#0000ee"), Color::NotSet.bg(Color::Rgb(0, 0, 0xee)));
+/// # }
+/// ```
+///
+/// Unknown words and invalid colors are compile errors:
+///
+/// ```compile_fail
+/// # #[macro_use] extern crate term_painter;
+/// # fn main() {
+/// let style = style!(bold redd);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! style {
+    // Attributes
+    (@style $s:expr; plain $($rest:tt)*) => { $crate::style!(@style $s; $($rest)*) };
+    (@style $s:expr; bold $($rest:tt)*) => { $crate::style!(@attr $s; Bold; $($rest)*) };
+    (@style $s:expr; dim $($rest:tt)*) => { $crate::style!(@attr $s; Dim; $($rest)*) };
+    (@style $s:expr; italic $($rest:tt)*) => { $crate::style!(@attr $s; Italic; $($rest)*) };
+    (@style $s:expr; underline - color = $($rest:tt)*) => {
+        $crate::style!(@color $s; underline_color; "is not a color"; $($rest)*)
+    };
+    (@style $s:expr; underline - style = $u:ident $($rest:tt)*) => {
+        $crate::style!(@style $s.with_underline_style(Some($crate::style!(@underline $u)));
+            $($rest)*)
+    };
+    (@style $s:expr; underline = $u:ident $($rest:tt)*) => {
+        $crate::style!(@underline_style $s; $u; $($rest)*)
+    };
+    (@style $s:expr; underline $($rest:tt)*) => { $crate::style!(@attr $s; Underline; $($rest)*) };
+    (@style $s:expr; blink $($rest:tt)*) => { $crate::style!(@attr $s; Blink; $($rest)*) };
+    (@style $s:expr; reverse $($rest:tt)*) => { $crate::style!(@attr $s; Reverse; $($rest)*) };
+    (@style $s:expr; secure $($rest:tt)*) => { $crate::style!(@attr $s; Secure; $($rest)*) };
+    (@style $s:expr; hidden $($rest:tt)*) => { $crate::style!(@attr $s; Secure; $($rest)*) };
+    (@style $s:expr; conceal $($rest:tt)*) => { $crate::style!(@attr $s; Secure; $($rest)*) };
+    (@style $s:expr; strikethrough $($rest:tt)*) => {
+        $crate::style!(@attr $s; Strikethrough; $($rest)*)
+    };
+    (@style $s:expr; overline $($rest:tt)*) => { $crate::style!(@attr $s; Overline; $($rest)*) };
+    (@style $s:expr; not - $a:ident $($rest:tt)*) => {
+        $crate::style!(@style $s.with_attr($crate::style!(@not $a), Some(false)); $($rest)*)
+    };
+    (@style $s:expr; $u:ident - underline $($rest:tt)*) => {
+        $crate::style!(@underline_style $s; $u; $($rest)*)
+    };
+
+    // Colors
+    (@style $s:expr; on $($rest:tt)*) => { $crate::style!(@color $s; bg; "is not a color"; $($rest)*) };
+    (@style $s:expr; fg = $($rest:tt)*) => { $crate::style!(@color $s; fg; "is not a color"; $($rest)*) };
+    (@style $s:expr; bg = $($rest:tt)*) => { $crate::style!(@color $s; bg; "is not a color"; $($rest)*) };
+    (@style $s:expr; $($rest:tt)+) => {
+        $crate::style!(@color $s; fg; "is neither a color nor an attribute"; $($rest)*)
+    };
+    (@style $s:expr;) => { $s };
+
+    (@attr $s:expr; $a:ident; $($rest:tt)*) => {
+        $crate::style!(@style $s.with_attr($crate::Attr::$a, Some(true)); $($rest)*)
+    };
+    (@underline_style $s:expr; $u:ident; $($rest:tt)*) => {
+        $crate::style!(@style $s.underline_style($crate::style!(@underline $u)); $($rest)*)
+    };
+
+    (@color $s:expr; $f:ident; $err:expr; none $($rest:tt)*) => { $crate::style!(@set $s; $f; NotSet; $($rest)*) };
+    (@color $s:expr; $f:ident; $err:expr; black $($rest:tt)*) => { $crate::style!(@set $s; $f; Black; $($rest)*) };
+    (@color $s:expr; $f:ident; $err:expr; red $($rest:tt)*) => { $crate::style!(@set $s; $f; Red; $($rest)*) };
+    (@color $s:expr; $f:ident; $err:expr; green $($rest:tt)*) => { $crate::style!(@set $s; $f; Green; $($rest)*) };
+    (@color $s:expr; $f:ident; $err:expr; yellow $($rest:tt)*) => { $crate::style!(@set $s; $f; Yellow; $($rest)*) };
+    (@color $s:expr; $f:ident; $err:expr; blue $($rest:tt)*) => { $crate::style!(@set $s; $f; Blue; $($rest)*) };
+    (@color $s:expr; $f:ident; $err:expr; magenta $($rest:tt)*) => { $crate::style!(@set $s; $f; Magenta; $($rest)*) };
+    (@color $s:expr; $f:ident; $err:expr; cyan $($rest:tt)*) => { $crate::style!(@set $s; $f; Cyan; $($rest)*) };
+    (@color $s:expr; $f:ident; $err:expr; white $($rest:tt)*) => { $crate::style!(@set $s; $f; White; $($rest)*) };
+    (@color $s:expr; $f:ident; $err:expr; bright - black $($rest:tt)*) => { $crate::style!(@set $s; $f; BrightBlack; $($rest)*) };
+    (@color $s:expr; $f:ident; $err:expr; bright - red $($rest:tt)*) => { $crate::style!(@set $s; $f; BrightRed; $($rest)*) };
+    (@color $s:expr; $f:ident; $err:expr; bright - green $($rest:tt)*) => { $crate::style!(@set $s; $f; BrightGreen; $($rest)*) };
+    (@color $s:expr; $f:ident; $err:expr; bright - yellow $($rest:tt)*) => { $crate::style!(@set $s; $f; BrightYellow; $($rest)*) };
+    (@color $s:expr; $f:ident; $err:expr; bright - blue $($rest:tt)*) => { $crate::style!(@set $s; $f; BrightBlue; $($rest)*) };
+    (@color $s:expr; $f:ident; $err:expr; bright - magenta $($rest:tt)*) => { $crate::style!(@set $s; $f; BrightMagenta; $($rest)*) };
+    (@color $s:expr; $f:ident; $err:expr; bright - cyan $($rest:tt)*) => { $crate::style!(@set $s; $f; BrightCyan; $($rest)*) };
+    (@color $s:expr; $f:ident; $err:expr; bright - white $($rest:tt)*) => { $crate::style!(@set $s; $f; BrightWhite; $($rest)*) };
+    (@color $s:expr; $f:ident; $err:expr; # $hex:tt $($rest:tt)*) => {
+        $crate::style!(@set $s; $f; $crate::parse_hex_const(stringify!($hex)); $($rest)*)
+    };
+    (@color $s:expr; $f:ident; $err:expr; $l:literal $($rest:tt)*) => {
+        $crate::style!(@set $s; $f; $crate::parse_literal_const(stringify!($l)); $($rest)*)
+    };
+    (@color $s:expr; $f:ident; $err:expr; $other:tt $($rest:tt)*) => {
+        compile_error!(concat!("`", stringify!($other), "` ", $err))
+    };
+    (@color $s:expr; $f:ident; $err:expr;) => { compile_error!("expected a color") };
+
+    (@set $s:expr; $f:ident; $c:ident; $($rest:tt)*) => {
+        $crate::style!(@set $s; $f; $crate::Color::$c; $($rest)*)
+    };
+    (@set $s:expr; $f:ident; $c:expr; $($rest:tt)*) => {
+        $crate::style!(@style $s.$f($c); $($rest)*)
+    };
+
+    (@not bold) => { $crate::Attr::Bold };
+    (@not dim) => { $crate::Attr::Dim };
+    (@not italic) => { $crate::Attr::Italic };
+    (@not underline) => { $crate::Attr::Underline };
+    (@not blink) => { $crate::Attr::Blink };
+    (@not reverse) => { $crate::Attr::Reverse };
+    (@not secure) => { $crate::Attr::Secure };
+    (@not hidden) => { $crate::Attr::Secure };
+    (@not conceal) => { $crate::Attr::Secure };
+    (@not strikethrough) => { $crate::Attr::Strikethrough };
+    (@not overline) => { $crate::Attr::Overline };
+    (@not $other:tt) => { compile_error!(concat!("unknown attribute `not-", stringify!($other), "`")) };
+
+    (@underline single) => { $crate::UnderlineStyle::Single };
+    (@underline double) => { $crate::UnderlineStyle::Double };
+    (@underline curly) => { $crate::UnderlineStyle::Curly };
+    (@underline dotted) => { $crate::UnderlineStyle::Dotted };
+    (@underline dashed) => { $crate::UnderlineStyle::Dashed };
+    (@underline $other:tt) => {
+        compile_error!(concat!(
+            "unknown underline style `", stringify!($other),
+            "` (expected `single`, `double`, `curly`, `dotted` or `dashed`)",
+        ))
+    };
+
+    ($($words:tt)*) => {{
+        // Evaluating the style in a constant turns errors (like invalid hex
+        // colors) into compile errors.
+        const STYLE: $crate::Style = $crate::style!(@style $crate::Style::new(); $($words)*);
+        STYLE
+    }};
+}
+
+/// Used by `style!`: parses the digits of a hex color (`rgb` or `rrggbb`).
+/// Panics if they are invalid, which is a compile error in a constant.
+#[doc(hidden)]
+pub const fn parse_hex_const(s: &str) -> Color {
+    parse_hex(s.as_bytes(), 0, s.len())
+}
+
+/// Used by `style!`: parses a literal color, which is either a number (see
+/// `Color::Custom`) or a hex color in a string (`"#rrggbb"`).
+/// Panics if it's invalid, like `parse_hex_const`.
+#[doc(hidden)]
+pub const fn parse_literal_const(s: &str) -> Color {
+    let d = s.as_bytes();
+    if let [b'"', b'#', .., b'"'] = d {
+        return parse_hex(d, 2, d.len() - 3);
+    }
+
+    let mut n: u32 = 0;
+    let mut i = 0;
+    while i < d.len() {
+        let next = match (d[i], n.checked_mul(10)) {
+            (b'0'..=b'9', Some(n)) => n.checked_add((d[i] - b'0') as u32),
+            _ => None,
+        };
+        n = match next {
+            Some(n) => n,
+            None => panic!("invalid color (expected a number or \"#rrggbb\")"),
+        };
+        i += 1;
+    }
+    Color::Custom(n)
+}
+
+/// Parses the `len` hex digits starting at `d[start]`. Slicing is not
+/// possible in a `const fn`, hence the offset.
+const fn parse_hex(d: &[u8], start: usize, len: usize) -> Color {
+    const fn digit(b: u8) -> u8 {
+        match b {
+            b'0'..=b'9' => b - b'0',
+            b'a'..=b'f' => b - b'a' + 10,
+            b'A'..=b'F' => b - b'A' + 10,
+            _ => panic!("invalid hex color (expected `#rgb` or `#rrggbb`)"),
+        }
+    }
+
+    let o = start;
+    match len {
+        3 => Color::Rgb(digit(d[o]) * 17, digit(d[o + 1]) * 17, digit(d[o + 2]) * 17),
+        6 => Color::Rgb(
+            digit(d[o]) * 16 + digit(d[o + 1]),
+            digit(d[o + 2]) * 16 + digit(d[o + 3]),
+            digit(d[o + 4]) * 16 + digit(d[o + 5]),
+        ),
+        _ => panic!("invalid hex color (expected `#rgb` or `#rrggbb`)"),
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use Color::*;
+    use Attr::*;
+    use {Style, ToStyle, UnderlineStyle};
+
+    const ERROR: Style = style!(bold bright-red on #1e1e1e);
+
+    #[test]
+    fn words() {
+        assert_eq!(style!(), Style::default());
+        assert_eq!(style!(plain), Style::default());
+        assert_eq!(ERROR, Bold.fg(BrightRed).bg(Rgb(30, 30, 30)));
+        assert_eq!(style!(red on blue italic), Red.bg(Blue).italic());
+        assert_eq!(style!(dim hidden not-underline), Dim.secure().not_underline());
+        assert_eq!(style!(fg=208 bg=#f80 red), Red.bg(Rgb(255, 136, 0)));
+        assert_eq!(style!(bg=none on black), Plain.bg(Black));
+        assert_eq!(
+            style!(dotted-underline underline-color=#123456),
+            Plain.underline_style(UnderlineStyle::Dotted).underline_color(Rgb(0x12, 0x34, 0x56)),
+        );
+        assert_eq!(
+            style!(on "#0000ee" fg="#12345e" underline-color="#abc" red on 0),
+            Red.bg(Custom(0)).underline_color(Rgb(0xaa, 0xbb, 0xcc)),
+        );
+        assert_eq!(style!(fg="#12345e"), Rgb(0x12, 0x34, 0x5e).to_style());
+        assert_eq!(
+            style!(underline-style=double underline=curly),
+            Plain.underline_style(UnderlineStyle::Curly),
+        );
+    }
+
+    #[test]
+    fn same_as_parsing() {
+        let words = [
+            "bold bright-red on #1e1e1e",
+            "not-bold blink reverse strikethrough overline conceal",
+            "underline-style=dashed bright-white on 17",
+            "#ABC on #00ff00",
+            "255 on #0000ee",
+        ];
+        let styles = [
+            style!(bold bright-red on #1e1e1e),
+            style!(not-bold blink reverse strikethrough overline conceal),
+            style!(underline-style=dashed bright-white on 17),
+            style!(#ABC on #00ff00),
+            style!(255 on "#0000ee"),
+        ];
+
+        for (w, &s) in words.iter().zip(&styles) {
+            assert_eq!(w.parse::<Style>(), Ok(s), "{}", w);
+        }
+    }
+}
@@ -0,0 +1,7 @@
+//! Helpers shared by the tests of several modules.
+
+/// Returns a lookup function for environment variables that only knows
+/// `vars`, to be passed to the `*_from_env` functions.
+pub fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+    move |name| vars.iter().find(|&&(n, _)| n == name).map(|&(_, v)| v.to_string())
+}
@@ -0,0 +1,364 @@
+//! Text made of multiple differently styled spans.
+
+use std::fmt;
+use std::iter::FromIterator;
+use std::ops::{Add, AddAssign, Bound, RangeBounds};
+
+use output;
+use {color_choice, visible_width, Painted, Style, Target, ToStyle, ANSI_STYLE};
+
+/// Text made of spans with different styles, like a compiler message that
+/// consists of a red label, a bold code and plain text:
+///
+/// ```
+/// # use term_painter::{Attr, Color, StyledText, ToStyle};
+/// let mut line = StyledText::new();
+/// line.push(Color::Red.bold(), "error");
+/// line.push(Attr::Bold, "[E0308]");
+/// line.push(Attr::Plain, ": mismatched types");
+/// println!("{}", line);
+///
+/// assert_eq!(line.width(), 30);
+/// assert_eq!(line.slice(..5).plain_text(), "error");
+/// ```
+///
+/// When printed, only the properties that change between two spans are sent
+/// to the terminal and the previous style is restored at the end. Like
+/// `Painted`, the text can be written to the terminal (the default) or as
+/// ANSI escape sequences (see `ansi()`). Width and alignment format
+/// specifiers (like `{:<20}`) pad with unstyled fill characters according to
+/// `width()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StyledText {
+    spans: Vec<(Style, String)>,
+    ansi: bool,
+    target: Target,
+}
+
+impl StyledText {
+    /// Creates an empty text.
+    pub fn new() -> Self {
+        StyledText {
+            spans: Vec::new(),
+            ansi: false,
+            target: Target::Stdout,
+        }
+    }
+
+    /// Appends `text` with the given style. If the last span has the same
+    /// style, `text` is added to it. Empty text is ignored.
+    pub fn push<S: ToStyle, T: Into<String>>(&mut self, style: S, text: T) {
+        let style = style.to_style();
+        let text = text.into();
+        if text.is_empty() {
+            return;
+        }
+
+        match self.spans.last_mut() {
+            Some(&mut (s, ref mut last)) if s == style => last.push_str(&text),
+            _ => self.spans.push((style, text)),
+        }
+    }
+
+    /// Appends all spans of `other`.
+    pub fn append(&mut self, other: StyledText) {
+        self.extend(other.spans);
+    }
+
+    /// Returns the spans. Adjacent spans always have different styles and
+    /// no span is empty.
+    pub fn spans(&self) -> &[(Style, String)] {
+        &self.spans
+    }
+
+    /// Returns the length of the text (without styling) in bytes.
+    pub fn len(&self) -> usize {
+        self.spans.iter().map(|(_, text)| text.len()).sum()
+    }
+
+    /// Returns whether there is no text.
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    /// Returns the number of columns the text takes up in the terminal (see
+    /// `visible_width`).
+    pub fn width(&self) -> usize {
+        self.spans.iter().map(|(_, text)| visible_width(text)).sum()
+    }
+
+    /// Returns the text without styling.
+    pub fn plain_text(&self) -> String {
+        self.spans.iter().map(|(_, text)| text.as_str()).collect()
+    }
+
+    /// Returns the part of the text in `range`, keeping the styles. Like
+    /// indexing a `str`, the range is given in bytes of the plain text.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds or doesn't start and end at
+    /// character boundaries.
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> StyledText {
+        let len = self.len();
+        let out_of_bounds = || -> ! {
+            panic!("range {:?}..{:?} out of bounds for text of length {}",
+                range.start_bound(), range.end_bound(), len)
+        };
+        let start = match range.start_bound() {
+            Bound::Included(&i) => i,
+            Bound::Excluded(&i) => i.checked_add(1).unwrap_or_else(|| out_of_bounds()),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&i) => i.checked_add(1).unwrap_or_else(|| out_of_bounds()),
+            Bound::Excluded(&i) => i,
+            Bound::Unbounded => len,
+        };
+        assert!(start <= end && end <= len, "range {}..{} out of bounds for text of length {}",
+            start, end, len);
+
+        let mut out = StyledText { spans: Vec::new(), ..*self };
+        let mut offset = 0;
+        for &(style, ref text) in &self.spans {
+            let (from, to) = (start.max(offset), end.min(offset + text.len()));
+            if from < to {
+                out.push(style, &text[from - offset..to - offset]);
+            }
+            offset += text.len();
+        }
+        out
+    }
+
+    /// Sets the output stream the styles are applied to when the text is
+    /// printed (see `Painted::target`).
+    pub fn target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Writes ANSI escape sequences into the formatter instead of changing
+    /// the state of the terminal (see `Painted::ansi`):
+    ///
+    /// ```
+    /// # use term_painter::{Attr, Color, StyledText, ToStyle};
+    /// let text: StyledText = vec![(Color::Red, "a"), (Color::Blue, "b")].into_iter().collect();
+    /// assert_eq!(text.ansi().to_string(), "\x1b[31ma\x1b[34mb\x1b[0m");
+    /// ```
+    pub fn ansi(mut self) -> Self {
+        self.ansi = true;
+        self
+    }
+
+    /// Applies the styles to the terminal while writing the spans.
+    fn fmt_term(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let mut spans = self.spans.iter();
+        let first = match spans.next() {
+            Some(first) => first,
+            None => return Ok(()),
+        };
+
+        output::push_style(self.target, first.0);
+        let mut res = f.write_str(&first.1);
+        for &(style, ref text) in spans {
+            if res.is_err() {
+                break;
+            }
+            output::replace_style(self.target, style);
+            res = f.write_str(text);
+        }
+        output::pop_style(self.target);
+
+        res
+    }
+
+    /// Writes the spans with ANSI escape sequences in between.
+    fn fmt_ansi(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        if !color_choice().enabled(None) {
+            return self.spans.iter().try_for_each(|(_, text)| f.write_str(text));
+        }
+
+        let base = ANSI_STYLE.with(|curr| curr.get()).unwrap_or_default();
+        let mut curr = base;
+        for &(style, ref text) in &self.spans {
+            let next = base.and(style);
+            write!(f, "{}{}", Style::transition(curr, next), text)?;
+            curr = next;
+        }
+        write!(f, "{}", Style::transition(curr, base))
+    }
+}
+
+impl Default for StyledText {
+    fn default() -> Self {
+        StyledText::new()
+    }
+}
+
+impl fmt::Display for StyledText {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let pad = f.width().map_or(0, |w| w.saturating_sub(self.width()));
+        let (before, after) = match f.align() {
+            Some(fmt::Alignment::Right) => (pad, 0),
+            Some(fmt::Alignment::Center) => (pad / 2, pad - pad / 2),
+            Some(fmt::Alignment::Left) | None => (0, pad),
+        };
+
+        let fill = f.fill();
+        for _ in 0..before {
+            write!(f, "{}", fill)?;
+        }
+        if self.ansi {
+            self.fmt_ansi(f)?;
+        } else {
+            self.fmt_term(f)?;
+        }
+        for _ in 0..after {
+            write!(f, "{}", fill)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<S: ToStyle, T: Into<String>> Extend<(S, T)> for StyledText {
+    fn extend<I: IntoIterator<Item = (S, T)>>(&mut self, iter: I) {
+        for (style, text) in iter {
+            self.push(style, text);
+        }
+    }
+}
+
+impl<S: ToStyle, T: Into<String>> FromIterator<(S, T)> for StyledText {
+    fn from_iter<I: IntoIterator<Item = (S, T)>>(iter: I) -> Self {
+        let mut text = StyledText::new();
+        text.extend(iter);
+        text
+    }
+}
+
+impl<'a> From<&'a str> for StyledText {
+    fn from(text: &'a str) -> Self {
+        StyledText::from(String::from(text))
+    }
+}
+
+impl From<String> for StyledText {
+    fn from(text: String) -> Self {
+        let mut out = StyledText::new();
+        out.push(Style::default(), text);
+        out
+    }
+}
+
+/// Keeps the style, the target and whether `ansi()` was used. A link set via
+/// `Painted::link` is dropped, as `StyledText` doesn't support links.
+impl<T: fmt::Display> From<Painted<T>> for StyledText {
+    fn from(painted: Painted<T>) -> Self {
+        let mut out = StyledText::new();
+        out.ansi = painted.ansi;
+        out.target = painted.target;
+        out.push(painted.style, painted.obj.to_string());
+        out
+    }
+}
+
+impl<T: Into<StyledText>> Add<T> for StyledText {
+    type Output = StyledText;
+
+    fn add(mut self, other: T) -> StyledText {
+        self += other;
+        self
+    }
+}
+
+impl<T: Into<StyledText>> AddAssign<T> for StyledText {
+    fn add_assign(&mut self, other: T) {
+        self.append(other.into());
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use Color::*;
+    use Attr::*;
+    use {ColorChoice, Target, ToStyle};
+    use super::StyledText;
+
+    fn error() -> StyledText {
+        StyledText::from(Red.bold().paint("error"))
+            + Bold.paint("[E0308]")
+            + ": mismatched types"
+    }
+
+    #[test]
+    fn building() {
+        let mut text = error();
+        assert_eq!(text.spans().len(), 3);
+        assert_eq!(text.plain_text(), "error[E0308]: mismatched types");
+        assert_eq!(text.len(), 30);
+
+        // Equal styles are merged and empty text is dropped
+        text.push(Plain, "!");
+        text.push(Red, "");
+        text.extend(vec![(Blue, "a"), (Blue, "b")]);
+        assert_eq!(text.spans().len(), 4);
+        assert_eq!(text.spans()[3], (Blue.to_style(), "ab".to_string()));
+
+        assert!(StyledText::new().is_empty());
+        assert_eq!(StyledText::from("ü漢").width(), 3);
+    }
+
+    #[test]
+    fn slicing() {
+        let text = error();
+        assert_eq!(text.slice(..), text);
+        assert_eq!(text.slice(3..7), StyledText::from(Red.bold().paint("or")) + Bold.paint("[E"));
+        assert_eq!(text.slice(12..=13).spans(), &[(Plain.to_style(), ": ".to_string())][..]);
+        assert!(text.slice(5..5).is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn slice_out_of_bounds() {
+        error().slice(20..40);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn slice_overflow() {
+        error().slice(..=usize::MAX);
+    }
+
+    #[test]
+    fn from_painted() {
+        let text = StyledText::from(Red.paint("a").ansi().target(Target::Stderr));
+        assert_eq!(text, StyledText::from(Red.paint("a")).ansi().target(Target::Stderr));
+    }
+
+    #[test]
+    fn display() {
+        let text = error().ansi();
+        ColorChoice::Always.with(|| {
+            assert_eq!(
+                text.to_string(),
+                "\x1b[31;1merror\x1b[39m[E0308]\x1b[0m: mismatched types",
+            );
+            assert_eq!(
+                format!("{:>8}|", StyledText::from(Red.paint("ab")).ansi()),
+                "      \x1b[31mab\x1b[0m|",
+            );
+
+            // Inside a `Painted`, the outer style is the base
+            let inner = StyledText::from(Bold.paint("b")).ansi();
+            assert_eq!(
+                Red.paint(inner).ansi().to_string(),
+                "\x1b[31m\x1b[1mb\x1b[22m\x1b[0m",
+            );
+        });
+        ColorChoice::Never.with(|| {
+            assert_eq!(format!("{:^7}", text.slice(..5)), " error ");
+        });
+    }
+}
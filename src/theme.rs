@@ -0,0 +1,322 @@
+//! Named collections of styles.
+
+use std::collections::btree_map::{self, BTreeMap};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::iter::FromIterator;
+use std::path::Path;
+use std::str::FromStr;
+
+use {Painted, ParseError, Style, ToStyle};
+
+/// Maps semantic names like `"error"` or `"path"` to styles, so that the
+/// styles of an application can be configured in one place (and loaded at
+/// runtime).
+///
+/// ```
+/// # use term_painter::Theme;
+/// let theme = Theme::dark();
+/// println!("{}: file not found", theme.paint("error", "error"));
+///
+/// // Themes can be parsed from `name = style` lines
+/// let theme: Theme = "error = bold red\npath = underline".parse().unwrap();
+/// println!("{}", theme.paint("path", "/tmp/foo"));
+/// ```
+///
+/// The built-in themes (`dark` and `light`) define these names: `error`,
+/// `warning`, `info`, `success`, `note`, `path`, `keyword`, `string`,
+/// `number`, `comment`, `heading`, `emphasis` and `link`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Theme {
+    styles: BTreeMap<String, Style>,
+}
+
+impl Theme {
+    /// Creates an empty theme.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Built-in theme for terminals with a dark background.
+    pub fn dark() -> Self {
+        Self::builtin(&[
+            ("error", "bold bright-red"),
+            ("warning", "bold bright-yellow"),
+            ("info", "bright-cyan"),
+            ("success", "bright-green"),
+            ("note", "bold bright-blue"),
+            ("path", "bright-cyan"),
+            ("keyword", "bold bright-magenta"),
+            ("string", "bright-green"),
+            ("number", "bright-yellow"),
+            ("comment", "bright-black"),
+            ("heading", "bold underline"),
+            ("emphasis", "italic"),
+            ("link", "underline bright-blue"),
+        ])
+    }
+
+    /// Built-in theme for terminals with a light background.
+    pub fn light() -> Self {
+        Self::builtin(&[
+            ("error", "bold red"),
+            ("warning", "bold 130"),
+            ("info", "cyan"),
+            ("success", "green"),
+            ("note", "bold blue"),
+            ("path", "cyan"),
+            ("keyword", "bold magenta"),
+            ("string", "green"),
+            ("number", "130"),
+            ("comment", "244"),
+            ("heading", "bold underline"),
+            ("emphasis", "italic"),
+            ("link", "underline blue"),
+        ])
+    }
+
+    fn builtin(styles: &[(&str, &str)]) -> Self {
+        styles.iter()
+            .map(|&(name, style)| (name.to_string(), style.parse().unwrap()))
+            .collect()
+    }
+
+    /// Loads a theme from a file. See `Theme`'s `FromStr` implementation for
+    /// the format.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ThemeError> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    /// Returns the style with the given name.
+    pub fn get(&self, name: &str) -> Option<Style> {
+        self.styles.get(name).cloned()
+    }
+
+    /// Returns the style with the given name or the default style if there
+    /// is no style with that name.
+    pub fn style(&self, name: &str) -> Style {
+        self.get(name).unwrap_or_default()
+    }
+
+    /// Adds a style with the given name, returning the style that had this
+    /// name before.
+    pub fn insert<S: Into<String>>(&mut self, name: S, style: Style) -> Option<Style> {
+        self.styles.insert(name.into(), style)
+    }
+
+    /// Removes the style with the given name and returns it.
+    pub fn remove(&mut self, name: &str) -> Option<Style> {
+        self.styles.remove(name)
+    }
+
+    /// Returns an iterator over all names and styles, sorted by name.
+    pub fn iter(&self) -> ThemeIter<'_> {
+        ThemeIter(self.styles.iter())
+    }
+
+    /// Paints `obj` with the style of the given name. If there is no style
+    /// with that name, `obj` is printed unstyled.
+    pub fn paint<T>(&self, name: &str, obj: T) -> Painted<T> {
+        self.style(name).paint(obj)
+    }
+
+    /// Like `ToStyle::with`, but with the style of the given name.
+    pub fn with<F, R>(&self, name: &str, f: F) -> R
+        where F: FnOnce() -> R
+    {
+        self.style(name).with(f)
+    }
+}
+
+/// Parses a theme from lines of the form `name = style`. The style is parsed
+/// with `Style`'s `FromStr` implementation. Empty lines and lines starting
+/// with `#` are ignored.
+///
+/// ```
+/// # use term_painter::{Color, Theme, ToStyle};
+/// let theme: Theme = "
+///     ## Errors should really stand out
+///     error = bold bright-red on black
+///     path = underline
+/// ".parse().unwrap();
+///
+/// assert_eq!(theme.get("error"), Some(Color::BrightRed.bold().bg(Color::Black)));
+/// ```
+impl FromStr for Theme {
+    type Err = ThemeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut theme = Theme::new();
+
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let pos = line.find('=').ok_or(ThemeError::MissingEquals { line: i + 1 })?;
+            let name = line[..pos].trim();
+            if name.is_empty() {
+                return Err(ThemeError::MissingEquals { line: i + 1 });
+            }
+            let style = line[pos + 1..].trim().parse().map_err(|e| ThemeError::Style {
+                line: i + 1,
+                name: name.to_string(),
+                error: e,
+            })?;
+
+            theme.insert(name, style);
+        }
+
+        Ok(theme)
+    }
+}
+
+/// Writes the theme in the format accepted by `FromStr`.
+impl fmt::Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, style) in self {
+            writeln!(f, "{} = {}", name, style)?;
+        }
+        Ok(())
+    }
+}
+
+impl<S: Into<String>> FromIterator<(S, Style)> for Theme {
+    fn from_iter<I: IntoIterator<Item = (S, Style)>>(iter: I) -> Self {
+        let mut theme = Theme::new();
+        theme.extend(iter);
+        theme
+    }
+}
+
+/// Adds all styles, overriding existing styles with the same name. This can
+/// be used to apply a user's configuration on top of a built-in theme.
+impl<S: Into<String>> Extend<(S, Style)> for Theme {
+    fn extend<I: IntoIterator<Item = (S, Style)>>(&mut self, iter: I) {
+        for (name, style) in iter {
+            self.insert(name, style);
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Theme {
+    type Item = (&'a str, Style);
+    type IntoIter = ThemeIter<'a>;
+
+    fn into_iter(self) -> ThemeIter<'a> {
+        self.iter()
+    }
+}
+
+/// Iterator over the names and styles of a theme, created by `Theme::iter`.
+pub struct ThemeIter<'a>(btree_map::Iter<'a, String, Style>);
+
+impl<'a> Iterator for ThemeIter<'a> {
+    type Item = (&'a str, Style);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(name, style)| (&**name, *style))
+    }
+}
+
+/// Error returned when loading or parsing a `Theme` fails.
+#[derive(Debug)]
+pub enum ThemeError {
+    /// Reading the file failed.
+    Io(io::Error),
+    /// The line (starting at 1) is not of the form `name = style`.
+    MissingEquals { line: usize },
+    /// The style in the given line (starting at 1) is invalid.
+    Style { line: usize, name: String, error: ParseError },
+}
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ThemeError::Io(ref e) => write!(f, "failed to read theme: {}", e),
+            ThemeError::MissingEquals { line } => {
+                write!(f, "line {}: expected `name = style`", line)
+            }
+            ThemeError::Style { line, ref name, ref error } => {
+                write!(f, "line {}: invalid style for `{}`: {}", line, name, error)
+            }
+        }
+    }
+}
+
+impl Error for ThemeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            ThemeError::Io(ref e) => Some(e),
+            ThemeError::MissingEquals { .. } => None,
+            ThemeError::Style { ref error, .. } => Some(error),
+        }
+    }
+}
+
+impl From<io::Error> for ThemeError {
+    fn from(e: io::Error) -> Self {
+        ThemeError::Io(e)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use Color::*;
+    use Attr::*;
+    use {ParseError, Style, ToStyle};
+    use super::{Theme, ThemeError};
+
+    #[test]
+    fn builtin() {
+        for theme in &[Theme::dark(), Theme::light()] {
+            assert_eq!(theme.iter().count(), 13);
+            assert!(theme.get("error").is_some());
+        }
+        assert_eq!(Theme::dark().get("error"), Some(BrightRed.bold()));
+    }
+
+    #[test]
+    fn lookup() {
+        let mut theme = Theme::new();
+        assert_eq!(theme.insert("error", Red.to_style()), None);
+        assert_eq!(theme.insert("error", Red.bold()), Some(Red.to_style()));
+        assert_eq!(theme.get("error"), Some(Red.bold()));
+        assert_eq!(theme.get("warning"), None);
+        assert_eq!(theme.style("warning"), Style::default());
+        assert_eq!(theme.remove("error"), Some(Red.bold()));
+        assert_eq!(theme.get("error"), None);
+    }
+
+    #[test]
+    fn parse() {
+        let theme: Theme = "\n  # comment\nerror=bold red\n  path =  underline \n".parse().unwrap();
+        assert_eq!(theme.get("error"), Some(Red.bold()));
+        assert_eq!(theme.get("path"), Some(Underline.to_style()));
+        assert_eq!(theme.to_string(), "error = bold red\npath = underline\n");
+        assert_eq!(theme.to_string().parse::<Theme>().unwrap(), theme);
+
+        match "error = bold\npath underline".parse::<Theme>() {
+            Err(ThemeError::MissingEquals { line: 2 }) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match "error = redd".parse::<Theme>() {
+            Err(ThemeError::Style { line: 1, ref name, error: ParseError::UnknownWord(_) })
+                if name == "error" => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn extend() {
+        let mut theme = Theme::dark();
+        theme.extend(vec![("error", Red.to_style()), ("custom", Blue.to_style())]);
+        assert_eq!(theme.get("error"), Some(Red.to_style()));
+        assert_eq!(theme.get("custom"), Some(Blue.to_style()));
+        assert_eq!(theme.get("warning"), Theme::dark().get("warning"));
+    }
+}
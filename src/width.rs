@@ -0,0 +1,48 @@
+//! Measuring how many columns text takes up in the terminal.
+
+use unicode_width::UnicodeWidthStr;
+
+use strip_ansi;
+
+/// Returns the number of columns `s` takes up when printed to a terminal.
+/// Escape sequences (see `strip_ansi`) take up no space, East Asian wide
+/// characters and emoji take up two columns and combining marks and other
+/// zero width characters none:
+///
+/// ```
+/// # use term_painter::{visible_width, Color, ColorChoice, ToStyle};
+/// let s = ColorChoice::Always.with(|| Color::Red.paint("エラー").ansi().to_string());
+/// assert_eq!(visible_width(&s), 6);
+/// assert_eq!(visible_width("cafe\u{301} 👍"), 7);
+/// ```
+///
+/// Useful to align text that has been styled already, for which format
+/// specifiers like `{:<20}` would count the escape sequences, too. Control
+/// characters (like `\t` or `\n`) don't have a fixed width and count as zero
+/// columns, so `s` should be a single line.
+pub fn visible_width(s: &str) -> usize {
+    strip_ansi(s)
+        .split(char::is_control)
+        .map(UnicodeWidthStr::width)
+        .sum()
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::visible_width;
+
+    #[test]
+    fn widths() {
+        assert_eq!(visible_width(""), 0);
+        assert_eq!(visible_width("abc"), 3);
+        assert_eq!(visible_width("\x1b[1;31mabc\x1b[0m"), 3);
+        assert_eq!(visible_width("\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\"), 4);
+        assert_eq!(visible_width("漢字"), 4);
+        assert_eq!(visible_width("e\u{301}\u{200b}"), 1);
+        assert_eq!(visible_width("👍🇩🇪"), 4);
+        assert_eq!(visible_width("👨\u{200d}👩\u{200d}👧"), 2);
+        assert_eq!(visible_width("❤\u{fe0f}"), 2);
+        assert_eq!(visible_width("a\tb\x07\n"), 2);
+    }
+}